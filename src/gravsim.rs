@@ -1,3 +1,12 @@
 pub mod application;
+pub mod crash;
+pub mod frame_pacer;
+pub mod keybindings;
+pub mod localization;
+pub mod logging;
+pub mod profiler;
+pub mod settings;
 pub mod shader;
+pub mod sim;
+pub mod ui_style;
 pub mod window_surface;
@@ -1,6 +1,9 @@
 use winit::{application::ApplicationHandler, event_loop::ActiveEventLoop};
 
-use crate::gravsim::window_surface::{RenderContext, WindowSurface};
+use crate::gravsim::{
+    localization::Locale,
+    window_surface::{RenderContext, WindowSurface},
+};
 
 /// The Application trait defines the interface for applications
 /// that can be run using the gravsim framework.
@@ -22,11 +25,21 @@ pub trait Application: Sized {
     /// This function is called once the application has started and the window and rendering context are ready.
     fn new(ws: &mut WindowSurface<Self>) -> Self;
 
+    /// Advances the application's state by `dt`, the wall-clock time since the previous frame.
+    /// This function is called every frame before `ui` and `render`.
+    fn update(&mut self, dt: std::time::Duration);
+
     /// Renders a frame for the application.
     /// This function is called every frame to allow the application to render its content.
     fn render(&mut self, context: &mut RenderContext);
 
-    fn ui(&mut self, ui: &mut imgui::Ui);
+    /// Draws the application's windows, in the locale the framework UI is shown in.
+    /// This function is called every frame unless the UI is hidden in presentation mode.
+    fn ui(&mut self, ui: &mut imgui::Ui, locale: Locale);
+
+    /// Draws overlays that stay visible while the rest of the UI is hidden, such as in presentation mode.
+    /// This function is called every frame after `ui`, with the locale the framework UI is shown in.
+    fn hud(&mut self, ui: &mut imgui::Ui, locale: Locale);
 }
 
 struct ApplicationWrapper<App: Application> {
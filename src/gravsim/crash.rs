@@ -0,0 +1,105 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use crate::gravsim::localization::{Locale, Text};
+
+/// Name of the marker file pointing at a crash report the user has not yet seen.
+const PENDING_REPORT_FILE: &str = "pending";
+
+static ADAPTER_INFO: OnceLock<String> = OnceLock::new();
+
+/// The directory crash reports are written to, under the platform's local data directory.
+pub fn report_dir() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|dir| dir.join("gravsim").join("crashes"))
+}
+
+/// Records the adapter in use so that it can be included in crash reports.
+pub fn set_adapter_info(info: &wgpu::AdapterInfo) {
+    let _ = ADAPTER_INFO.set(format!(
+        "{} ({:?}, {:?}, driver {} {})",
+        info.name, info.device_type, info.backend, info.driver, info.driver_info
+    ));
+}
+
+/// Installs a panic hook that writes a crash report before running the default hook.
+///
+/// The report contains the panic message and location, a backtrace, the adapter in use,
+/// and the most recent log lines.
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        match write_report(info) {
+            Ok(path) => eprintln!("Crash report written to {}", path.display()),
+            Err(e) => eprintln!("Failed to write crash report: {:?}", e),
+        }
+        default_hook(info);
+    }));
+}
+
+fn write_report(info: &std::panic::PanicHookInfo) -> anyhow::Result<PathBuf> {
+    let dir = report_dir().ok_or(anyhow::anyhow!("No local data directory"))?;
+    std::fs::create_dir_all(&dir)?;
+
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs();
+    let path = dir.join(format!("crash-{}.txt", timestamp));
+
+    let mut file = std::fs::File::create(&path)?;
+    writeln!(file, "gravsim {} crashed", env!("CARGO_PKG_VERSION"))?;
+    writeln!(file, "Time: {} (unix)", timestamp)?;
+    writeln!(file, "Panic: {}", info)?;
+    writeln!(
+        file,
+        "Adapter: {}",
+        ADAPTER_INFO.get().map_or("not initialized", String::as_str)
+    )?;
+    writeln!(file)?;
+    writeln!(file, "Backtrace:")?;
+    writeln!(file, "{}", std::backtrace::Backtrace::force_capture())?;
+    writeln!(file, "Recent log:")?;
+    for line in crate::gravsim::logging::recent_lines() {
+        writeln!(file, "{}", line)?;
+    }
+
+    std::fs::write(
+        dir.join(PENDING_REPORT_FILE),
+        path.to_string_lossy().as_bytes(),
+    )?;
+
+    Ok(path)
+}
+
+/// Returns the crash report from a previous session that the user has not dismissed yet.
+pub fn pending_report() -> Option<PathBuf> {
+    let marker = report_dir()?.join(PENDING_REPORT_FILE);
+    let path = std::fs::read_to_string(marker).ok()?;
+    Some(PathBuf::from(path.trim()))
+}
+
+fn dismiss_report() {
+    if let Some(dir) = report_dir() {
+        let _ = std::fs::remove_file(dir.join(PENDING_REPORT_FILE));
+    }
+}
+
+/// Draws the dialog telling the user the previous session crashed.
+/// Returns false once the dialog has been dismissed.
+pub fn report_dialog(ui: &imgui::Ui, locale: Locale, report: &Path) -> bool {
+    let mut open = true;
+    ui.window(format!("{}###Crash", locale.text(Text::CrashTitle)))
+        .always_auto_resize(true)
+        .collapsible(false)
+        .build(|| {
+            ui.text(locale.text(Text::CrashMessage));
+            ui.text(report.to_string_lossy());
+            if ui.button(locale.text(Text::Dismiss)) {
+                dismiss_report();
+                open = false;
+            }
+        });
+    open
+}
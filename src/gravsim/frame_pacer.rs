@@ -0,0 +1,50 @@
+use std::time::{Duration, Instant};
+
+/// How long before a frame is due the pacer stops sleeping and starts spinning.
+/// OS sleeps routinely overshoot by a millisecond or more, so the final stretch is busy-waited.
+const SPIN_MARGIN: Duration = Duration::from_micros(1500);
+
+/// Holds frames to a steady wall-clock rate independently of vsync.
+pub struct FramePacer {
+    next_frame: Instant,
+}
+
+impl FramePacer {
+    pub fn new() -> Self {
+        Self {
+            next_frame: Instant::now(),
+        }
+    }
+
+    /// Blocks until the next frame is due at `rate` frames per second.
+    /// If the caller has fallen more than a frame behind, the schedule is restarted from now
+    /// rather than rendering a burst of frames to catch up.
+    pub fn wait(&mut self, rate: u32) {
+        let period = Duration::from_secs_f64(1.0 / rate.max(1) as f64);
+        let now = Instant::now();
+
+        if now > self.next_frame + period {
+            self.next_frame = now;
+            return;
+        }
+
+        if let Some(sleep) = self
+            .next_frame
+            .checked_duration_since(now)
+            .and_then(|remaining| remaining.checked_sub(SPIN_MARGIN))
+        {
+            std::thread::sleep(sleep);
+        }
+        while Instant::now() < self.next_frame {
+            std::hint::spin_loop();
+        }
+
+        self.next_frame += period;
+    }
+
+    /// Restarts the schedule from now. Called on unthrottled frames so that re-enabling
+    /// pacing does not start from a stale deadline.
+    pub fn reset(&mut self) {
+        self.next_frame = Instant::now();
+    }
+}
@@ -0,0 +1,97 @@
+use winit::keyboard::{Key, NamedKey};
+
+use crate::gravsim::localization::{Locale, Text};
+
+/// Actions handled by the framework itself, independent of the running application.
+/// Each action toggles the feature it is named after.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Fullscreen,
+    Help,
+    Settings,
+    Logging,
+    Profiler,
+    Presentation,
+}
+
+/// Binds a key to a framework action.
+/// The category and description are used to generate the help overlay.
+pub struct KeyBinding {
+    pub action: Action,
+    pub key: NamedKey,
+    pub category: Text,
+    pub description: Text,
+}
+
+/// Every key binding known to the framework, in the order they are listed in the help overlay.
+pub const KEY_BINDINGS: &[KeyBinding] = &[
+    KeyBinding {
+        action: Action::Help,
+        key: NamedKey::F1,
+        category: Text::CategoryGeneral,
+        description: Text::ToggleHelp,
+    },
+    KeyBinding {
+        action: Action::Settings,
+        key: NamedKey::F2,
+        category: Text::CategoryGeneral,
+        description: Text::ToggleSettings,
+    },
+    KeyBinding {
+        action: Action::Logging,
+        key: NamedKey::F3,
+        category: Text::CategoryGeneral,
+        description: Text::ToggleLogging,
+    },
+    KeyBinding {
+        action: Action::Profiler,
+        key: NamedKey::F4,
+        category: Text::CategoryGeneral,
+        description: Text::ToggleProfiler,
+    },
+    KeyBinding {
+        action: Action::Presentation,
+        key: NamedKey::F9,
+        category: Text::CategoryWindow,
+        description: Text::TogglePresentation,
+    },
+    KeyBinding {
+        action: Action::Fullscreen,
+        key: NamedKey::F11,
+        category: Text::CategoryWindow,
+        description: Text::ToggleFullscreen,
+    },
+];
+
+/// Finds the action bound to `key`, if any.
+pub fn action_for_key(key: &Key) -> Option<Action> {
+    KEY_BINDINGS
+        .iter()
+        .find(|binding| *key == Key::Named(binding.key))
+        .map(|binding| binding.action)
+}
+
+/// Draws the help overlay listing every key binding grouped by category.
+pub fn help_window(ui: &imgui::Ui, locale: Locale, opened: &mut bool) {
+    ui.window(format!("{}###Help", locale.text(Text::HelpTitle)))
+        .opened(opened)
+        .always_auto_resize(true)
+        .build(|| {
+            let mut categories: Vec<Text> = Vec::new();
+            for binding in KEY_BINDINGS {
+                if !categories.contains(&binding.category) {
+                    categories.push(binding.category);
+                }
+            }
+
+            for category in categories {
+                ui.separator();
+                ui.text_disabled(locale.text(category));
+                for binding in KEY_BINDINGS.iter().filter(|b| b.category == category) {
+                    ui.text(format!("{:?}", binding.key));
+                    ui.same_line_with_pos(80.0);
+                    ui.text(locale.text(binding.description));
+                }
+            }
+        });
+}
@@ -0,0 +1,434 @@
+/// A language the framework UI can be displayed in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    English,
+    German,
+}
+
+/// Every piece of UI text shown by the framework and the application.
+/// Each locale provides a translation for every variant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Text {
+    HelpTitle,
+    SettingsTitle,
+    CategoryGeneral,
+    CategoryWindow,
+    ToggleHelp,
+    ToggleSettings,
+    ToggleFullscreen,
+    Language,
+    UiScale,
+    Theme,
+    ThemeDark,
+    ThemeLight,
+    ThemeClassic,
+    KeyboardNavigation,
+    LimitFrameRate,
+    TargetFrameRate,
+    CrashTitle,
+    CrashMessage,
+    Dismiss,
+    ToggleLogging,
+    LoggingTitle,
+    LogGroupSimulation,
+    LogGroupFramework,
+    LogGroupGpu,
+    LogLevelDefault,
+    TraceFrameCount,
+    TraceFrames,
+    LogFile,
+    ToggleProfiler,
+    ProfilerTitle,
+    Pause,
+    Paused,
+    TogglePresentation,
+    WatchdogTitle,
+    WatchdogPausedAt,
+    WatchdogBodyCount,
+    WatchdogHint,
+    Body,
+    Position,
+    Velocity,
+    More,
+    WatchdogLastGood,
+    WatchdogNoLastGood,
+    Restore,
+    Bodies,
+    InteractionMatrixTitle,
+    InteractionMatrixLimit,
+    Quantity,
+    Force,
+    PotentialEnergy,
+    LogScale,
+    NoInteractingPairs,
+    Range,
+    To,
+    And,
+    SimulationTitle,
+    Energy,
+    EnergyLimit,
+    Softening,
+    Solver,
+    Direct,
+    BarnesHut,
+    Theta,
+    IncrementalTree,
+    TreeBudget,
+    Integrator,
+    SemiImplicitEuler,
+    Leapfrog,
+    VelocityVerlet,
+    RungeKutta4,
+    Yoshida8,
+    BouncingCollisions,
+    Restitution,
+    Boundary,
+    BoundaryNone,
+    BoundaryBox,
+    BoundarySphere,
+    BoundarySize,
+    Walls,
+    Reflect,
+    Absorb,
+    Timestep,
+    Fixed,
+    Adaptive,
+    Step,
+    Accuracy,
+    MinStep,
+    MaxStep,
+    LastStep,
+    InitialConditions,
+    Units,
+    UnitsNBody,
+    UnitsSolarSystem,
+    UnitsGalactic,
+    OrbitingBodies,
+    TotalMass,
+    Mass,
+    ScaleRadius,
+    Seed,
+    PlummerSphere,
+    Disc,
+    Bulge,
+    Halo,
+    ScaleLength,
+    ScaleHeight,
+    DiscGalaxy,
+    Separation,
+    ImpactParameter,
+    RelativeSpeed,
+    Inclination,
+    GalaxyCollision,
+    ColorByMass,
+    TimeScale,
+    Zoom,
+}
+
+impl Locale {
+    pub const ALL: &[Locale] = &[Locale::English, Locale::German];
+
+    /// Picks the locale matching the `LC_ALL`, `LC_MESSAGES` or `LANG` environment variables,
+    /// in that order of precedence, falling back to English.
+    pub fn from_env() -> Self {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    /// Like [`Self::from_env`], but reading variables through `var`.
+    /// Empty variables are treated as unset, as POSIX specifies.
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Self {
+        let lang = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .filter_map(var)
+            .find(|value| !value.is_empty())
+            .unwrap_or_default();
+        if lang.starts_with("de") {
+            Locale::German
+        } else {
+            Locale::English
+        }
+    }
+
+    /// The name of the locale in its own language, for use in the language selector.
+    pub fn name(self) -> &'static str {
+        match self {
+            Locale::English => "English",
+            Locale::German => "Deutsch",
+        }
+    }
+
+    pub fn text(self, text: Text) -> &'static str {
+        match self {
+            Locale::English => match text {
+                Text::HelpTitle => "Help",
+                Text::SettingsTitle => "Settings",
+                Text::CategoryGeneral => "General",
+                Text::CategoryWindow => "Window",
+                Text::ToggleHelp => "Show or hide this help",
+                Text::ToggleSettings => "Show or hide the settings",
+                Text::ToggleFullscreen => "Toggle fullscreen",
+                Text::Language => "Language",
+                Text::UiScale => "UI scale",
+                Text::Theme => "Theme",
+                Text::ThemeDark => "Dark",
+                Text::ThemeLight => "Light",
+                Text::ThemeClassic => "Classic",
+                Text::KeyboardNavigation => "Keyboard navigation",
+                Text::LimitFrameRate => "Limit frame rate",
+                Text::TargetFrameRate => "Frames per second",
+                Text::CrashTitle => "Previous session crashed",
+                Text::CrashMessage => "A crash report was saved to:",
+                Text::Dismiss => "Dismiss",
+                Text::ToggleLogging => "Show or hide the logging settings",
+                Text::LoggingTitle => "Logging",
+                Text::LogGroupSimulation => "Simulation",
+                Text::LogGroupFramework => "Framework",
+                Text::LogGroupGpu => "GPU",
+                Text::LogLevelDefault => "Default (RUST_LOG)",
+                Text::TraceFrameCount => "Frames",
+                Text::TraceFrames => "Trace frames",
+                Text::LogFile => "Log file:",
+                Text::ToggleProfiler => "Show or hide the profiler",
+                Text::ProfilerTitle => "Profiler",
+                Text::Pause => "Pause",
+                Text::Paused => "Paused",
+                Text::TogglePresentation => "Presentation mode (hide all UI)",
+                Text::WatchdogTitle => "Numerical blow-up",
+                Text::WatchdogPausedAt => "The simulation was paused at t =",
+                Text::WatchdogBodyCount => "Bodies with non-finite positions or velocities:",
+                Text::WatchdogHint => "Try a smaller timestep or a larger softening length.",
+                Text::Body => "Body",
+                Text::Position => "position",
+                Text::Velocity => "velocity",
+                Text::More => "more",
+                Text::WatchdogLastGood => "Last known good state: t =",
+                Text::WatchdogNoLastGood => "No known good state to restore.",
+                Text::Restore => "Restore",
+                Text::Bodies => "Bodies",
+                Text::InteractionMatrixTitle => "Interaction matrix",
+                Text::InteractionMatrixLimit => {
+                    "Only shown for systems of at most this many bodies:"
+                }
+                Text::Quantity => "Quantity",
+                Text::Force => "Force",
+                Text::PotentialEnergy => "Potential energy",
+                Text::LogScale => "Log scale",
+                Text::NoInteractingPairs => "No interacting pairs.",
+                Text::Range => "Range:",
+                Text::To => "to",
+                Text::And => "and",
+                Text::SimulationTitle => "Simulation",
+                Text::Energy => "Energy:",
+                Text::EnergyLimit => "Energy is not computed for more bodies than:",
+                Text::Softening => "Softening",
+                Text::Solver => "Solver",
+                Text::Direct => "Direct",
+                Text::BarnesHut => "Barnes-Hut",
+                Text::Theta => "Theta",
+                Text::IncrementalTree => "Incremental tree",
+                Text::TreeBudget => "Tree budget (ms)",
+                Text::Integrator => "Integrator",
+                Text::SemiImplicitEuler => "Semi-implicit Euler",
+                Text::Leapfrog => "Leapfrog",
+                Text::VelocityVerlet => "Velocity Verlet",
+                Text::RungeKutta4 => "Runge-Kutta 4",
+                Text::Yoshida8 => "Yoshida (8th order)",
+                Text::BouncingCollisions => "Bouncing collisions",
+                Text::Restitution => "Restitution",
+                Text::Boundary => "Boundary",
+                Text::BoundaryNone => "None",
+                Text::BoundaryBox => "Box",
+                Text::BoundarySphere => "Sphere",
+                Text::BoundarySize => "Boundary size",
+                Text::Walls => "Walls",
+                Text::Reflect => "Reflect",
+                Text::Absorb => "Absorb",
+                Text::Timestep => "Timestep",
+                Text::Fixed => "Fixed",
+                Text::Adaptive => "Adaptive",
+                Text::Step => "Step",
+                Text::Accuracy => "Accuracy",
+                Text::MinStep => "Min step",
+                Text::MaxStep => "Max step",
+                Text::LastStep => "Last step:",
+                Text::InitialConditions => "Initial conditions",
+                Text::Units => "Units",
+                Text::UnitsNBody => "N-body",
+                Text::UnitsSolarSystem => "Solar system (AU, M☉, day)",
+                Text::UnitsGalactic => "Galactic (kpc, M☉, Myr)",
+                Text::OrbitingBodies => "Orbiting bodies",
+                Text::TotalMass => "Total mass",
+                Text::Mass => "Mass",
+                Text::ScaleRadius => "Scale radius",
+                Text::Seed => "Seed",
+                Text::PlummerSphere => "Plummer sphere",
+                Text::Disc => "Disc",
+                Text::Bulge => "Bulge",
+                Text::Halo => "Halo",
+                Text::ScaleLength => "Scale length",
+                Text::ScaleHeight => "Scale height",
+                Text::DiscGalaxy => "Disc galaxy",
+                Text::Separation => "Separation",
+                Text::ImpactParameter => "Impact parameter",
+                Text::RelativeSpeed => "Relative speed",
+                Text::Inclination => "Inclination (°)",
+                Text::GalaxyCollision => "Galaxy collision",
+                Text::ColorByMass => "Colour by mass",
+                Text::TimeScale => "Time scale",
+                Text::Zoom => "Zoom",
+            },
+            Locale::German => match text {
+                Text::HelpTitle => "Hilfe",
+                Text::SettingsTitle => "Einstellungen",
+                Text::CategoryGeneral => "Allgemein",
+                Text::CategoryWindow => "Fenster",
+                Text::ToggleHelp => "Diese Hilfe ein- oder ausblenden",
+                Text::ToggleSettings => "Einstellungen ein- oder ausblenden",
+                Text::ToggleFullscreen => "Vollbild umschalten",
+                Text::Language => "Sprache",
+                Text::UiScale => "UI-Skalierung",
+                Text::Theme => "Farbschema",
+                Text::ThemeDark => "Dunkel",
+                Text::ThemeLight => "Hell",
+                Text::ThemeClassic => "Klassisch",
+                Text::KeyboardNavigation => "Tastaturnavigation",
+                Text::LimitFrameRate => "Bildrate begrenzen",
+                Text::TargetFrameRate => "Bilder pro Sekunde",
+                Text::CrashTitle => "Letzte Sitzung abgestürzt",
+                Text::CrashMessage => "Ein Absturzbericht wurde gespeichert unter:",
+                Text::Dismiss => "Schließen",
+                Text::ToggleLogging => "Protokolleinstellungen ein- oder ausblenden",
+                Text::LoggingTitle => "Protokoll",
+                Text::LogGroupSimulation => "Simulation",
+                Text::LogGroupFramework => "Framework",
+                Text::LogGroupGpu => "GPU",
+                Text::LogLevelDefault => "Standard (RUST_LOG)",
+                Text::TraceFrameCount => "Bilder",
+                Text::TraceFrames => "Bilder protokollieren",
+                Text::LogFile => "Protokolldatei:",
+                Text::ToggleProfiler => "Profiler ein- oder ausblenden",
+                Text::ProfilerTitle => "Profiler",
+                Text::Pause => "Anhalten",
+                Text::Paused => "Angehalten",
+                Text::TogglePresentation => "Präsentationsmodus (gesamte UI ausblenden)",
+                Text::WatchdogTitle => "Numerische Instabilität",
+                Text::WatchdogPausedAt => "Die Simulation wurde angehalten bei t =",
+                Text::WatchdogBodyCount => {
+                    "Körper mit nicht endlicher Position oder Geschwindigkeit:"
+                }
+                Text::WatchdogHint => {
+                    "Versuchen Sie einen kleineren Zeitschritt oder eine größere Glättungslänge."
+                }
+                Text::Body => "Körper",
+                Text::Position => "Position",
+                Text::Velocity => "Geschwindigkeit",
+                Text::More => "weitere",
+                Text::WatchdogLastGood => "Letzter gültiger Zustand: t =",
+                Text::WatchdogNoLastGood => "Kein gültiger Zustand zum Wiederherstellen.",
+                Text::Restore => "Wiederherstellen",
+                Text::Bodies => "Körper",
+                Text::InteractionMatrixTitle => "Wechselwirkungsmatrix",
+                Text::InteractionMatrixLimit => "Nur für Systeme mit höchstens so vielen Körpern:",
+                Text::Quantity => "Größe",
+                Text::Force => "Kraft",
+                Text::PotentialEnergy => "Potentielle Energie",
+                Text::LogScale => "Logarithmische Skala",
+                Text::NoInteractingPairs => "Keine wechselwirkenden Paare.",
+                Text::Range => "Bereich:",
+                Text::To => "bis",
+                Text::And => "und",
+                Text::SimulationTitle => "Simulation",
+                Text::Energy => "Energie:",
+                Text::EnergyLimit => "Die Energie wird nicht berechnet für mehr Körper als:",
+                Text::Softening => "Glättungslänge",
+                Text::Solver => "Löser",
+                Text::Direct => "Direkt",
+                Text::BarnesHut => "Barnes-Hut",
+                Text::Theta => "Theta",
+                Text::IncrementalTree => "Inkrementeller Baum",
+                Text::TreeBudget => "Baumbudget (ms)",
+                Text::Integrator => "Integrator",
+                Text::SemiImplicitEuler => "Semi-implizites Euler-Verfahren",
+                Text::Leapfrog => "Leapfrog",
+                Text::VelocityVerlet => "Velocity-Verlet",
+                Text::RungeKutta4 => "Runge-Kutta 4",
+                Text::Yoshida8 => "Yoshida (8. Ordnung)",
+                Text::BouncingCollisions => "Abprallende Kollisionen",
+                Text::Restitution => "Stoßzahl",
+                Text::Boundary => "Begrenzung",
+                Text::BoundaryNone => "Keine",
+                Text::BoundaryBox => "Würfel",
+                Text::BoundarySphere => "Kugel",
+                Text::BoundarySize => "Begrenzungsgröße",
+                Text::Walls => "Wände",
+                Text::Reflect => "Reflektieren",
+                Text::Absorb => "Absorbieren",
+                Text::Timestep => "Zeitschritt",
+                Text::Fixed => "Fest",
+                Text::Adaptive => "Adaptiv",
+                Text::Step => "Schritt",
+                Text::Accuracy => "Genauigkeit",
+                Text::MinStep => "Min. Schritt",
+                Text::MaxStep => "Max. Schritt",
+                Text::LastStep => "Letzter Schritt:",
+                Text::InitialConditions => "Anfangsbedingungen",
+                Text::Units => "Einheiten",
+                Text::UnitsNBody => "N-Körper",
+                Text::UnitsSolarSystem => "Sonnensystem (AE, M☉, Tag)",
+                Text::UnitsGalactic => "Galaktisch (kpc, M☉, Mio. Jahre)",
+                Text::OrbitingBodies => "Umlaufende Körper",
+                Text::TotalMass => "Gesamtmasse",
+                Text::Mass => "Masse",
+                Text::ScaleRadius => "Skalenradius",
+                Text::Seed => "Startwert",
+                Text::PlummerSphere => "Plummer-Kugel",
+                Text::Disc => "Scheibe",
+                Text::Bulge => "Bulge",
+                Text::Halo => "Halo",
+                Text::ScaleLength => "Skalenlänge",
+                Text::ScaleHeight => "Skalenhöhe",
+                Text::DiscGalaxy => "Scheibengalaxie",
+                Text::Separation => "Abstand",
+                Text::ImpactParameter => "Stoßparameter",
+                Text::RelativeSpeed => "Relativgeschwindigkeit",
+                Text::Inclination => "Neigung (°)",
+                Text::GalaxyCollision => "Galaxienkollision",
+                Text::ColorByMass => "Nach Masse färben",
+                Text::TimeScale => "Zeitfaktor",
+                Text::Zoom => "Zoom",
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Locale;
+
+    fn from(vars: &[(&str, &str)]) -> Locale {
+        Locale::from_vars(|name| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.to_string())
+        })
+    }
+
+    #[test]
+    fn empty_variables_count_as_unset() {
+        assert_eq!(
+            from(&[("LC_ALL", ""), ("LANG", "de_DE.UTF-8")]),
+            Locale::German
+        );
+        assert_eq!(from(&[("LC_ALL", ""), ("LANG", "")]), Locale::English);
+        assert_eq!(from(&[]), Locale::English);
+    }
+
+    #[test]
+    fn lc_messages_takes_precedence_over_lang() {
+        let vars = [("LC_MESSAGES", "de_AT.UTF-8"), ("LANG", "en_GB.UTF-8")];
+        assert_eq!(from(&vars), Locale::German);
+        let vars = [("LC_ALL", "en_US.UTF-8"), ("LC_MESSAGES", "de_DE.UTF-8")];
+        assert_eq!(from(&vars), Locale::English);
+    }
+}
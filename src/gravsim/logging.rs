@@ -0,0 +1,322 @@
+use std::{
+    collections::VecDeque,
+    io::Write,
+    path::PathBuf,
+    sync::{
+        Mutex, OnceLock,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use log::LevelFilter;
+
+use crate::gravsim::localization::{Locale, Text};
+
+/// How many of the most recent log lines are kept in memory for crash reports.
+const RECENT_LINE_CAPACITY: usize = 200;
+
+/// Size at which the log file is rotated, and how many rotated files are kept.
+const LOG_FILE_MAX_BYTES: u64 = 4 * 1024 * 1024;
+const LOG_FILE_ROTATIONS: usize = 3;
+
+static RECENT_LINES: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+/// Per-group level overrides set from the logging window. `None` uses the `RUST_LOG` filter.
+static GROUP_LEVELS: Mutex<[Option<LevelFilter>; LogGroup::ALL.len()]> =
+    Mutex::new([None; LogGroup::ALL.len()]);
+
+/// The most verbose level allowed by the `RUST_LOG` filter.
+static DEFAULT_MAX_LEVEL: OnceLock<LevelFilter> = OnceLock::new();
+
+/// Frames left in frame-trace mode, during which every record is logged.
+static TRACE_FRAMES_REMAINING: AtomicU64 = AtomicU64::new(0);
+
+/// A set of log targets whose level can be adjusted together at runtime.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogGroup {
+    Simulation,
+    Framework,
+    Gpu,
+}
+
+impl LogGroup {
+    pub const ALL: [LogGroup; 3] = [LogGroup::Simulation, LogGroup::Framework, LogGroup::Gpu];
+
+    /// Target prefixes belonging to the group. Groups are matched in order,
+    /// so the simulation is matched before the framework that contains it.
+    fn prefixes(self) -> &'static [&'static str] {
+        match self {
+            LogGroup::Simulation => &["gravsim::gravsim::sim"],
+            LogGroup::Framework => &["gravsim::gravsim"],
+            LogGroup::Gpu => &["wgpu", "naga"],
+        }
+    }
+
+    fn for_target(target: &str) -> Option<LogGroup> {
+        LogGroup::ALL.into_iter().find(|group| {
+            group
+                .prefixes()
+                .iter()
+                .any(|prefix| target.starts_with(prefix))
+        })
+    }
+
+    fn name(self) -> Text {
+        match self {
+            LogGroup::Simulation => Text::LogGroupSimulation,
+            LogGroup::Framework => Text::LogGroupFramework,
+            LogGroup::Gpu => Text::LogGroupGpu,
+        }
+    }
+}
+
+/// Appends to a log file, rotating it once it grows past `LOG_FILE_MAX_BYTES`.
+struct LogFile {
+    path: PathBuf,
+    file: std::fs::File,
+    written: u64,
+}
+
+impl LogFile {
+    fn open(path: PathBuf) -> std::io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)?;
+        let written = file.metadata()?.len();
+        Ok(Self {
+            path,
+            file,
+            written,
+        })
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        self.path.with_extension(format!("{}.log", index))
+    }
+
+    fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        if self.written >= LOG_FILE_MAX_BYTES {
+            for index in (1..LOG_FILE_ROTATIONS).rev() {
+                let _ = std::fs::rename(self.rotated_path(index), self.rotated_path(index + 1));
+            }
+            std::fs::rename(&self.path, self.rotated_path(1))?;
+            *self = Self::open(self.path.clone())?;
+        }
+        writeln!(self.file, "{}", line)?;
+        self.written += line.len() as u64 + 1;
+        Ok(())
+    }
+}
+
+/// Writes records to stderr through env_logger, to the log file, and to the recent line buffer.
+struct Logger {
+    stderr: env_logger::Logger,
+    default_filter: env_filter::Filter,
+    file: Mutex<Option<LogFile>>,
+}
+
+impl Logger {
+    fn level_for(&self, metadata: &log::Metadata) -> Option<LevelFilter> {
+        if TRACE_FRAMES_REMAINING.load(Ordering::Relaxed) > 0 {
+            return Some(LevelFilter::Trace);
+        }
+        let group = LogGroup::for_target(metadata.target())?;
+        GROUP_LEVELS.lock().ok()?[group as usize]
+    }
+}
+
+impl log::Log for Logger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        match self.level_for(metadata) {
+            Some(level) => metadata.level() <= level,
+            None => self.default_filter.enabled(metadata),
+        }
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        self.stderr.log(record);
+
+        let line = format!("[{} {}] {}", record.level(), record.target(), record.args());
+
+        if let Ok(mut file) = self.file.lock()
+            && let Some(log_file) = file.as_mut()
+            && let Err(e) = log_file.write_line(&line)
+        {
+            // Stop writing to the file rather than reporting the failure through the logger.
+            eprintln!("Failed to write log file: {}", e);
+            *file = None;
+        }
+
+        if let Ok(mut lines) = RECENT_LINES.lock() {
+            if lines.len() == RECENT_LINE_CAPACITY {
+                lines.pop_front();
+            }
+            lines.push_back(line);
+        }
+    }
+
+    fn flush(&self) {
+        self.stderr.flush();
+        if let Ok(mut file) = self.file.lock()
+            && let Some(log_file) = file.as_mut()
+        {
+            let _ = log_file.file.flush();
+        }
+    }
+}
+
+/// The file all log output is written to, under the platform's local data directory.
+pub fn log_file_path() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|dir| dir.join("gravsim").join("logs").join("gravsim.log"))
+}
+
+/// Installs the global logger. Levels default to the `RUST_LOG` filter, like `env_logger::init`,
+/// and can be overridden per group at runtime from the logging window.
+pub fn init() {
+    let default_filter = env_filter::Builder::from_env("RUST_LOG").build();
+    // Filtering is done by `Logger`, so env_logger only formats and writes. It must not see
+    // `RUST_LOG`, since it would otherwise apply its directives again to every record and drop
+    // the group overrides and frame traces.
+    let mut stderr = env_logger::Builder::new();
+    stderr.filter_level(LevelFilter::Trace);
+    if let Ok(write_style) = std::env::var("RUST_LOG_STYLE") {
+        stderr.parse_write_style(&write_style);
+    }
+    let stderr = stderr.build();
+
+    let file = log_file_path().and_then(|path| match LogFile::open(path.clone()) {
+        Ok(file) => Some(file),
+        Err(e) => {
+            eprintln!("Failed to open log file {:?}: {}", path, e);
+            None
+        }
+    });
+
+    let max_level = default_filter.filter();
+    log::set_boxed_logger(Box::new(Logger {
+        stderr,
+        default_filter,
+        file: Mutex::new(file),
+    }))
+    .expect("Logger already initialized");
+    log::set_max_level(max_level);
+    let _ = DEFAULT_MAX_LEVEL.set(max_level);
+}
+
+/// Raises or lowers the global maximum level to the most verbose level any group needs,
+/// so records below it are skipped without reaching the logger.
+fn update_max_level() {
+    let mut max_level = DEFAULT_MAX_LEVEL.get().copied().unwrap_or(LevelFilter::Off);
+    if TRACE_FRAMES_REMAINING.load(Ordering::Relaxed) > 0 {
+        max_level = LevelFilter::Trace;
+    }
+    if let Ok(levels) = GROUP_LEVELS.lock() {
+        for level in levels.iter().flatten() {
+            max_level = max_level.max(*level);
+        }
+    }
+    log::set_max_level(max_level);
+}
+
+/// Logs every record, regardless of level, for the next `frames` frames.
+pub fn trace_frames(frames: u64) {
+    log::info!("Tracing all log output for {} frames", frames);
+    TRACE_FRAMES_REMAINING.store(frames, Ordering::Relaxed);
+    update_max_level();
+}
+
+/// Marks the end of a frame, counting down frame-trace mode.
+pub fn end_frame() {
+    let remaining = TRACE_FRAMES_REMAINING.load(Ordering::Relaxed);
+    if remaining > 0 {
+        TRACE_FRAMES_REMAINING.store(remaining - 1, Ordering::Relaxed);
+        if remaining == 1 {
+            update_max_level();
+            log::info!("Frame trace finished");
+        }
+    }
+}
+
+/// Returns a copy of the most recent log lines, oldest first.
+pub fn recent_lines() -> Vec<String> {
+    RECENT_LINES
+        .lock()
+        .map(|lines| lines.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// The state of the logging window between frames.
+pub struct LoggingWindow {
+    trace_frames: i32,
+}
+
+impl Default for LoggingWindow {
+    fn default() -> Self {
+        Self { trace_frames: 10 }
+    }
+}
+
+impl LoggingWindow {
+    /// Draws the logging window, applying level changes immediately.
+    pub fn build(&mut self, ui: &imgui::Ui, locale: Locale, opened: &mut bool) {
+        const LEVELS: [Option<LevelFilter>; 7] = [
+            None,
+            Some(LevelFilter::Off),
+            Some(LevelFilter::Error),
+            Some(LevelFilter::Warn),
+            Some(LevelFilter::Info),
+            Some(LevelFilter::Debug),
+            Some(LevelFilter::Trace),
+        ];
+
+        ui.window(format!("{}###Logging", locale.text(Text::LoggingTitle)))
+            .opened(opened)
+            .always_auto_resize(true)
+            .build(|| {
+                let mut changed = false;
+                if let Ok(mut levels) = GROUP_LEVELS.lock() {
+                    for group in LogGroup::ALL {
+                        let level = &mut levels[group as usize];
+                        let mut index = LEVELS.iter().position(|l| l == level).unwrap_or_default();
+                        if ui.combo(
+                            locale.text(group.name()),
+                            &mut index,
+                            &LEVELS,
+                            |l| match l {
+                                Some(level) => level.as_str().into(),
+                                None => locale.text(Text::LogLevelDefault).into(),
+                            },
+                        ) {
+                            *level = LEVELS[index];
+                            changed = true;
+                        }
+                    }
+                }
+                if changed {
+                    update_max_level();
+                }
+
+                ui.separator();
+                ui.input_int(locale.text(Text::TraceFrameCount), &mut self.trace_frames)
+                    .step(10)
+                    .build();
+                self.trace_frames = self.trace_frames.max(1);
+                if ui.button(locale.text(Text::TraceFrames)) {
+                    trace_frames(self.trace_frames as u64);
+                }
+
+                if let Some(path) = log_file_path() {
+                    ui.separator();
+                    ui.text(locale.text(Text::LogFile));
+                    ui.text_disabled(path.to_string_lossy());
+                }
+            });
+    }
+}
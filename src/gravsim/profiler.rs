@@ -0,0 +1,194 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use crate::gravsim::localization::{Locale, Text};
+
+/// How many completed frames are kept for the frame time graph.
+const FRAME_HISTORY: usize = 300;
+
+/// A timed scope within a frame.
+#[derive(Clone, Debug)]
+struct ScopeRecord {
+    name: &'static str,
+    depth: usize,
+    /// Offset from the start of the frame.
+    start: Duration,
+    duration: Duration,
+}
+
+#[derive(Clone, Debug)]
+struct FrameProfile {
+    start: Instant,
+    duration: Duration,
+    scopes: Vec<ScopeRecord>,
+}
+
+impl FrameProfile {
+    fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            duration: Duration::ZERO,
+            scopes: Vec::new(),
+        }
+    }
+}
+
+struct Profiler {
+    history: VecDeque<FrameProfile>,
+    current: FrameProfile,
+    depth: usize,
+}
+
+thread_local! {
+    static PROFILER: RefCell<Profiler> = RefCell::new(Profiler {
+        history: VecDeque::with_capacity(FRAME_HISTORY),
+        current: FrameProfile::new(),
+        depth: 0,
+    });
+}
+
+/// Times the enclosing scope until dropped. Created with [`scope`].
+pub struct Scope {
+    frame_start: Instant,
+    index: usize,
+}
+
+/// Starts timing a named scope on the current thread. Scopes nest, and appear
+/// in the profiler window under the scope that was open when they started.
+/// ```rust
+/// let _scope = profiler::scope("Tree build");
+/// ```
+pub fn scope(name: &'static str) -> Scope {
+    PROFILER.with_borrow_mut(|profiler| {
+        let start = profiler.current.start.elapsed();
+        profiler.current.scopes.push(ScopeRecord {
+            name,
+            depth: profiler.depth,
+            start,
+            duration: Duration::ZERO,
+        });
+        profiler.depth += 1;
+        Scope {
+            frame_start: profiler.current.start,
+            index: profiler.current.scopes.len() - 1,
+        }
+    })
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        PROFILER.with_borrow_mut(|profiler| {
+            // Scopes still open when their frame ended are left out of the profile.
+            if profiler.current.start != self.frame_start {
+                return;
+            }
+            let now = profiler.current.start.elapsed();
+            let record = &mut profiler.current.scopes[self.index];
+            record.duration = now - record.start;
+            profiler.depth = profiler.depth.saturating_sub(1);
+        });
+    }
+}
+
+/// Completes the current frame on this thread and starts recording the next one.
+pub fn new_frame() {
+    PROFILER.with_borrow_mut(|profiler| {
+        let mut finished = std::mem::replace(&mut profiler.current, FrameProfile::new());
+        finished.duration = finished.start.elapsed();
+        if profiler.history.len() == FRAME_HISTORY {
+            profiler.history.pop_front();
+        }
+        profiler.history.push_back(finished);
+        profiler.depth = 0;
+    });
+}
+
+/// The state of the profiler window between frames.
+#[derive(Default)]
+pub struct ProfilerWindow {
+    /// A copy of the frame being inspected while paused.
+    paused: Option<FrameProfile>,
+}
+
+impl ProfilerWindow {
+    pub fn build(&mut self, ui: &imgui::Ui, locale: Locale, opened: &mut bool) {
+        ui.window(format!("{}###Profiler", locale.text(Text::ProfilerTitle)))
+            .opened(opened)
+            .size([600.0, 300.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                PROFILER.with_borrow(|profiler| {
+                    let frame_times: Vec<f32> = profiler
+                        .history
+                        .iter()
+                        .map(|frame| frame.duration.as_secs_f32() * 1000.0)
+                        .collect();
+                    let latest = profiler.history.back();
+
+                    let mut paused = self.paused.is_some();
+                    if ui.checkbox(locale.text(Text::Pause), &mut paused) {
+                        self.paused = if paused { latest.cloned() } else { None };
+                    }
+
+                    let average = frame_times.iter().sum::<f32>() / frame_times.len().max(1) as f32;
+                    ui.same_line();
+                    ui.text(format!("{:.2} ms ({:.0} fps)", average, 1000.0 / average));
+
+                    ui.plot_lines("##FrameTimes", &frame_times)
+                        .graph_size([ui.content_region_avail()[0], 60.0])
+                        .scale_min(0.0)
+                        .build();
+
+                    if let Some(frame) = self.paused.as_ref().or(latest) {
+                        Self::flame_graph(ui, frame);
+                    }
+                });
+            });
+    }
+
+    /// Draws each scope as a bar proportional to its share of the frame, nested scopes below their parent.
+    fn flame_graph(ui: &imgui::Ui, frame: &FrameProfile) {
+        const COLORS: [[f32; 4]; 4] = [
+            [0.90, 0.45, 0.20, 1.0],
+            [0.85, 0.65, 0.20, 1.0],
+            [0.35, 0.65, 0.85, 1.0],
+            [0.45, 0.75, 0.40, 1.0],
+        ];
+
+        let row_height = ui.text_line_height_with_spacing() + 4.0;
+        let origin = ui.cursor_screen_pos();
+        let width = ui.content_region_avail()[0];
+        let depth = frame.scopes.iter().map(|s| s.depth + 1).max().unwrap_or(1);
+        let frame_secs = frame.duration.as_secs_f32().max(f32::EPSILON);
+        let draw_list = ui.get_window_draw_list();
+        let mouse = ui.io().mouse_pos;
+
+        for scope in &frame.scopes {
+            let x0 = origin[0] + scope.start.as_secs_f32() / frame_secs * width;
+            let x1 = x0 + (scope.duration.as_secs_f32() / frame_secs * width).max(1.0);
+            let y0 = origin[1] + scope.depth as f32 * row_height;
+            let y1 = y0 + row_height - 1.0;
+
+            draw_list
+                .add_rect([x0, y0], [x1, y1], COLORS[scope.depth % COLORS.len()])
+                .filled(true)
+                .build();
+
+            let label = format!(
+                "{} {:.2} ms",
+                scope.name,
+                scope.duration.as_secs_f64() * 1000.0
+            );
+            if ui.calc_text_size(&label)[0] < x1 - x0 - 4.0 {
+                draw_list.add_text([x0 + 2.0, y0 + 2.0], [0.0, 0.0, 0.0, 1.0], &label);
+            }
+            if (x0..x1).contains(&mouse[0]) && (y0..y1).contains(&mouse[1]) {
+                ui.tooltip_text(&label);
+            }
+        }
+
+        ui.dummy([width, depth as f32 * row_height]);
+    }
+}
@@ -0,0 +1,194 @@
+use std::path::PathBuf;
+
+use crate::gravsim::{
+    localization::{Locale, Text},
+    ui_style::Theme,
+};
+
+/// User adjustable settings for the framework, edited through the settings window.
+pub struct Settings {
+    pub locale: Locale,
+    /// Multiplier applied on top of the window's scale factor to the UI fonts and style.
+    pub ui_scale: f32,
+    pub theme: Theme,
+    /// Lets the UI be driven entirely from the keyboard (arrow keys, space, tab).
+    pub keyboard_navigation: bool,
+    /// Holds rendering to `target_frame_rate` instead of running as fast as possible.
+    pub limit_frame_rate: bool,
+    pub target_frame_rate: u32,
+    pub fonts: FontSettings,
+    pub gpu_debug: GpuDebugSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            locale: Locale::from_env(),
+            ui_scale: 1.0,
+            theme: Theme::default(),
+            keyboard_navigation: true,
+            limit_frame_rate: true,
+            target_frame_rate: 60,
+            fonts: FontSettings::from_env(),
+            gpu_debug: GpuDebugSettings::from_env(),
+        }
+    }
+}
+
+/// The set of characters rasterised from the UI font.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GlyphRanges {
+    /// Basic Latin and Latin-1 Supplement.
+    #[default]
+    Latin,
+    Cyrillic,
+    Japanese,
+    Korean,
+    ChineseFull,
+    Thai,
+    Vietnamese,
+}
+
+impl GlyphRanges {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "latin" => Some(GlyphRanges::Latin),
+            "cyrillic" => Some(GlyphRanges::Cyrillic),
+            "japanese" => Some(GlyphRanges::Japanese),
+            "korean" => Some(GlyphRanges::Korean),
+            "chinese" => Some(GlyphRanges::ChineseFull),
+            "thai" => Some(GlyphRanges::Thai),
+            "vietnamese" => Some(GlyphRanges::Vietnamese),
+            _ => None,
+        }
+    }
+}
+
+/// Fonts used by the UI, read once at startup.
+///
+/// - `GRAVSIM_UI_FONT`: path to a TTF/OTF file replacing imgui's built-in font.
+/// - `GRAVSIM_UI_FONT_GLYPHS`: glyph ranges to load from it, one of `latin`, `cyrillic`,
+///   `japanese`, `korean`, `chinese`, `thai` or `vietnamese`.
+/// - `GRAVSIM_ICON_FONT`: path to an icon font (e.g. Font Awesome) merged into the UI font.
+#[derive(Debug, Default)]
+pub struct FontSettings {
+    pub ui_font: Option<PathBuf>,
+    pub glyph_ranges: GlyphRanges,
+    pub icon_font: Option<PathBuf>,
+}
+
+impl FontSettings {
+    pub fn from_env() -> Self {
+        let glyph_ranges = match std::env::var("GRAVSIM_UI_FONT_GLYPHS") {
+            Ok(name) => GlyphRanges::from_name(&name).unwrap_or_else(|| {
+                log::warn!("Unknown glyph ranges {:?}, using latin", name);
+                GlyphRanges::Latin
+            }),
+            Err(_) => GlyphRanges::Latin,
+        };
+
+        Self {
+            ui_font: std::env::var_os("GRAVSIM_UI_FONT").map(PathBuf::from),
+            glyph_ranges,
+            icon_font: std::env::var_os("GRAVSIM_ICON_FONT").map(PathBuf::from),
+        }
+    }
+}
+
+/// Options for diagnosing rendering problems, read once at startup.
+///
+/// - `GRAVSIM_WGPU_VALIDATION`: `1` enables the backend validation layers and debug labels,
+///   `0` disables them. By default they are on only in debug builds.
+///
+/// The standard `WGPU_*` instance environment variables are applied on top of this.
+#[derive(Debug, Default)]
+pub struct GpuDebugSettings {
+    pub validation: Option<bool>,
+}
+
+impl GpuDebugSettings {
+    pub fn from_env() -> Self {
+        Self {
+            validation: std::env::var("GRAVSIM_WGPU_VALIDATION")
+                .ok()
+                .map(|value| value != "0"),
+        }
+    }
+
+    pub fn instance_flags(&self) -> wgpu::InstanceFlags {
+        match self.validation {
+            Some(true) => wgpu::InstanceFlags::debugging(),
+            Some(false) => wgpu::InstanceFlags::empty(),
+            None => wgpu::InstanceFlags::default(),
+        }
+        .with_env()
+    }
+}
+
+/// Draws the settings window, applying any changes directly to `settings`.
+pub fn settings_window(ui: &imgui::Ui, settings: &mut Settings, opened: &mut bool) {
+    let locale = settings.locale;
+    ui.window(format!("{}###Settings", locale.text(Text::SettingsTitle)))
+        .opened(opened)
+        .always_auto_resize(true)
+        .build(|| {
+            let mut index = Locale::ALL
+                .iter()
+                .position(|l| *l == settings.locale)
+                .unwrap_or_default();
+            if ui.combo(locale.text(Text::Language), &mut index, Locale::ALL, |l| {
+                l.name().into()
+            }) {
+                settings.locale = Locale::ALL[index];
+            }
+
+            // Rebuilding the fonts changes the size of every widget, so the scale is
+            // stepped rather than dragged to keep the control stable under the cursor.
+            let mut ui_scale = settings.ui_scale;
+            if ui
+                .input_float(locale.text(Text::UiScale), &mut ui_scale)
+                .step(0.25)
+                .display_format("%.2f")
+                .enter_returns_true(true)
+                .build()
+            {
+                settings.ui_scale = ui_scale.clamp(0.5, 3.0);
+            }
+
+            let mut index = Theme::ALL
+                .iter()
+                .position(|t| *t == settings.theme)
+                .unwrap_or_default();
+            if ui.combo(locale.text(Text::Theme), &mut index, Theme::ALL, |t| {
+                locale
+                    .text(match t {
+                        Theme::Dark => Text::ThemeDark,
+                        Theme::Light => Text::ThemeLight,
+                        Theme::Classic => Text::ThemeClassic,
+                    })
+                    .into()
+            }) {
+                settings.theme = Theme::ALL[index];
+            }
+
+            ui.checkbox(
+                locale.text(Text::KeyboardNavigation),
+                &mut settings.keyboard_navigation,
+            );
+
+            ui.checkbox(
+                locale.text(Text::LimitFrameRate),
+                &mut settings.limit_frame_rate,
+            );
+            if settings.limit_frame_rate {
+                let mut rate = settings.target_frame_rate as i32;
+                if ui
+                    .input_int(locale.text(Text::TargetFrameRate), &mut rate)
+                    .step(10)
+                    .build()
+                {
+                    settings.target_frame_rate = rate.clamp(1, 1000) as u32;
+                }
+            }
+        });
+}
@@ -29,3 +29,208 @@ pub struct FragmentShader<'a> {
     pub module: &'a wgpu::ShaderModule,
     pub entry_point: Option<&'a str>,
 }
+
+/// A set of preprocessor defines substituted into WGSL source before compilation.
+/// Defines are ordered so that the same set always produces the same cache key.
+///
+/// Inside the shader, a define can be used as a value, where every identifier
+/// matching its name is replaced with the value, or in conditional blocks:
+/// ```wgsl
+/// @compute @workgroup_size(WORKGROUP_SIZE)
+/// fn main() {
+/// #ifdef USE_SOFTENING
+///     ...
+/// #else
+///     ...
+/// #endif
+/// }
+/// ```
+/// `#if NAME` is also supported, and is taken unless the value is `0` or `false`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ShaderDefines(std::collections::BTreeMap<String, String>);
+
+impl ShaderDefines {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds (or replaces) a define, returning the updated set.
+    pub fn with(mut self, name: &str, value: impl ToString) -> Self {
+        self.0.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    fn is_truthy(&self, name: &str) -> bool {
+        self.0
+            .get(name)
+            .is_some_and(|value| value != "0" && value != "false")
+    }
+
+    /// Runs the preprocessor over `source`, returning the WGSL to compile.
+    pub fn preprocess(&self, source: &str) -> anyhow::Result<String> {
+        // Each open conditional block tracks whether its current branch is active,
+        // and whether an `#else` has already been seen.
+        let mut blocks: Vec<(bool, bool)> = Vec::new();
+        let mut output = String::with_capacity(source.len());
+
+        for (line_number, line) in source.lines().enumerate() {
+            let line_number = line_number + 1;
+            let enclosing_active = blocks.iter().all(|(active, _)| *active);
+            let trimmed = line.trim_start();
+
+            if let Some(directive) = trimmed.strip_prefix('#') {
+                let mut parts = directive.split_whitespace();
+                let keyword = parts.next().unwrap_or_default();
+                let argument = parts.next();
+                match (keyword, argument) {
+                    ("ifdef", Some(name)) => blocks.push((self.0.contains_key(name), false)),
+                    ("ifndef", Some(name)) => blocks.push((!self.0.contains_key(name), false)),
+                    ("if", Some(name)) => blocks.push((self.is_truthy(name), false)),
+                    ("else", None) => match blocks.last_mut() {
+                        Some((active, seen_else)) if !*seen_else => {
+                            *active = !*active;
+                            *seen_else = true;
+                        }
+                        Some(_) => anyhow::bail!("Duplicate #else on line {}", line_number),
+                        None => anyhow::bail!("#else without #if on line {}", line_number),
+                    },
+                    ("endif", None) => {
+                        if blocks.pop().is_none() {
+                            anyhow::bail!("#endif without #if on line {}", line_number);
+                        }
+                    }
+                    _ => anyhow::bail!(
+                        "Unknown preprocessor directive `{}` on line {}",
+                        line.trim(),
+                        line_number
+                    ),
+                }
+                // Keep line numbers in compiler errors matching the original source.
+                output.push('\n');
+                continue;
+            }
+
+            if enclosing_active {
+                self.substitute_line(line, &mut output);
+            }
+            output.push('\n');
+        }
+
+        if !blocks.is_empty() {
+            anyhow::bail!("Missing #endif for {} conditional block(s)", blocks.len());
+        }
+
+        Ok(output)
+    }
+
+    fn substitute_line(&self, line: &str, output: &mut String) {
+        let mut rest = line;
+        while let Some(start) = rest.find(|c: char| c.is_ascii_alphabetic() || c == '_') {
+            output.push_str(&rest[..start]);
+            let end = rest[start..]
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .map_or(rest.len(), |end| start + end);
+            let identifier = &rest[start..end];
+            // Letters directly following a digit are part of a numeric literal (e.g. `1e5`, `2u`).
+            if output.ends_with(|c: char| c.is_ascii_digit()) {
+                output.push_str(identifier);
+            } else {
+                output.push_str(self.0.get(identifier).map_or(identifier, String::as_str));
+            }
+            rest = &rest[end..];
+        }
+        output.push_str(rest);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ShaderDefines;
+
+    #[test]
+    fn nested_blocks_follow_their_enclosing_branch() {
+        let source = concat!(
+            "#ifdef OUTER\n",
+            "outer\n",
+            "#ifdef INNER\n",
+            "inner\n",
+            "#else\n",
+            "not_inner\n",
+            "#endif\n",
+            "#else\n",
+            "not_outer\n",
+            "#ifdef INNER\n",
+            "not_outer_inner\n",
+            "#endif\n",
+            "#endif\n",
+        );
+        let lines = |defines: &ShaderDefines| -> Vec<String> {
+            defines
+                .preprocess(source)
+                .unwrap()
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect()
+        };
+
+        let both = ShaderDefines::new().with("OUTER", 1).with("INNER", 1);
+        assert_eq!(lines(&both), ["outer", "inner"]);
+        let outer = ShaderDefines::new().with("OUTER", 1);
+        assert_eq!(lines(&outer), ["outer", "not_inner"]);
+        let inner = ShaderDefines::new().with("INNER", 1);
+        assert_eq!(lines(&inner), ["not_outer", "not_outer_inner"]);
+        // The inner `#else` must not reveal its branch while the outer block is not taken.
+        assert_eq!(lines(&ShaderDefines::new()), ["not_outer"]);
+    }
+
+    #[test]
+    fn directives_keep_line_numbers() {
+        let output = ShaderDefines::new()
+            .preprocess("a\n#ifdef X\nb\n#endif\nc\n")
+            .unwrap();
+        assert_eq!(output, "a\n\n\n\nc\n");
+    }
+
+    #[test]
+    fn if_treats_zero_and_false_as_not_taken() {
+        let source = "#if FLAG\ntaken\n#endif\n";
+        for (value, taken) in [("1", true), ("0", false), ("false", false), ("true", true)] {
+            let output = ShaderDefines::new()
+                .with("FLAG", value)
+                .preprocess(source)
+                .unwrap();
+            assert_eq!(output.contains("taken"), taken, "FLAG = {value}");
+        }
+    }
+
+    #[test]
+    fn values_replace_whole_identifiers_only() {
+        let output = ShaderDefines::new()
+            .with("SIZE", 64)
+            .preprocess("@workgroup_size(SIZE) let x = SIZE_MAX + 1e5 * SIZE;")
+            .unwrap();
+        assert_eq!(output, "@workgroup_size(64) let x = SIZE_MAX + 1e5 * 64;\n");
+    }
+
+    #[test]
+    fn unterminated_block_is_an_error() {
+        let error = ShaderDefines::new()
+            .preprocess("#ifdef X\n#ifdef Y\n#endif\n")
+            .unwrap_err();
+        assert!(error.to_string().contains("Missing #endif"), "{error}");
+    }
+
+    #[test]
+    fn unbalanced_directives_are_errors() {
+        let defines = ShaderDefines::new();
+        assert!(defines.preprocess("#endif\n").is_err());
+        assert!(defines.preprocess("#else\n").is_err());
+        assert!(
+            defines
+                .preprocess("#ifdef X\n#else\n#else\n#endif\n")
+                .is_err()
+        );
+        assert!(defines.preprocess("#include \"x.wgsl\"\n").is_err());
+    }
+}
@@ -0,0 +1,514 @@
+pub mod barnes_hut;
+pub mod boundary;
+pub mod collisions;
+pub mod direct;
+pub mod initial_conditions;
+pub mod integrator;
+pub mod interaction_matrix;
+pub mod spatial_hash;
+pub mod units;
+pub mod watchdog;
+
+use std::time::{Duration, Instant};
+
+use glam::DVec3;
+
+use crate::gravsim::profiler;
+
+/// The method used to compute the gravitational forces between bodies.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Solver {
+    /// Sums over every pair of bodies. Exact, but O(N²).
+    Direct,
+    /// Approximates distant groups of bodies by their centre of mass, O(N log N).
+    /// `theta` is the opening angle: smaller values are more accurate and slower.
+    ///
+    /// Without a `tree_budget` the tree is rebuilt on every force evaluation. With one, it is
+    /// updated incrementally, spending at most the budget per frame reinserting bodies that have
+    /// moved between cells, which avoids the frame-time spikes of full rebuilds at large N.
+    BarnesHut {
+        theta: f64,
+        tree_budget: Option<Duration>,
+    },
+}
+
+/// How [`Simulation::advance`] divides time into steps.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Timestep {
+    /// Steps of exactly `dt`, which keeps the symplectic integrators' energy errors bounded.
+    /// Time that does not fill a whole step is carried over to the next advance.
+    Fixed { dt: f64 },
+    /// Steps sized so that the fastest-accelerating body's acceleration alone would move it
+    /// `accuracy` length units, i.e. `dt = sqrt(2 * accuracy / max |a|)`, clamped to `min..=max`.
+    /// Steps shrink during close encounters and grow again once the system is quiet. Time that
+    /// does not fill the next step is carried over to the next advance.
+    Adaptive { accuracy: f64, min: f64, max: f64 },
+}
+
+/// The most steps a single [`Simulation::advance`] may take.
+const MAX_STEPS_PER_ADVANCE: usize = 1000;
+
+/// The most wall-clock time a single [`Simulation::advance`] may spend stepping.
+const ADVANCE_TIME_LIMIT: Duration = Duration::from_millis(100);
+
+/// The parameters controlling how a simulation is advanced. Can be changed between steps.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SimulationConfig {
+    /// The units bodies are described in, which fix the gravitational constant.
+    pub units: units::UnitSystem,
+    /// The Plummer softening length. Gravity is weakened between bodies closer than about this,
+    /// so that close passes do not produce the huge accelerations of point masses.
+    pub softening: f64,
+    pub solver: Solver,
+    pub integrator: integrator::IntegratorKind,
+    pub timestep: Timestep,
+    pub collisions: collisions::Collisions,
+    /// Walls keeping bodies within a region, or `None` to let them roam freely.
+    pub boundary: Option<boundary::Boundary>,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            units: units::UnitSystem::default(),
+            softening: 0.01,
+            solver: Solver::Direct,
+            integrator: integrator::IntegratorKind::default(),
+            timestep: Timestep::Fixed { dt: 1.0 / 240.0 },
+            collisions: collisions::Collisions::default(),
+            boundary: None,
+        }
+    }
+}
+
+/// A copy of the bodies and elapsed time that a simulation can later be restored to.
+#[derive(Clone)]
+pub struct Snapshot {
+    positions: Vec<DVec3>,
+    velocities: Vec<DVec3>,
+    masses: Vec<f64>,
+    radii: Vec<f64>,
+    time: f64,
+}
+
+impl Snapshot {
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+}
+
+/// An N-body gravitational simulation.
+///
+/// Bodies are stored as parallel arrays of positions, velocities, masses and radii,
+/// indexed by the order they were added in.
+/// ```rust
+/// let mut sim = Simulation::new(SimulationConfig::default());
+/// sim.add_body(DVec3::ZERO, DVec3::ZERO, 1000.0, 1.0);
+/// sim.add_body(DVec3::new(10.0, 0.0, 0.0), DVec3::new(0.0, 10.0, 0.0), 1.0, 0.1);
+/// sim.step(0.01);
+/// ```
+pub struct Simulation {
+    positions: Vec<DVec3>,
+    velocities: Vec<DVec3>,
+    masses: Vec<f64>,
+    /// Only used for collisions; gravity treats bodies as (softened) points.
+    radii: Vec<f64>,
+    config: SimulationConfig,
+    time: f64,
+    octree: barnes_hut::Octree,
+    integrator: Box<dyn integrator::Integrator>,
+    collision_state: collisions::CollisionState,
+    /// The largest acceleration magnitude seen in the most recent force evaluation.
+    max_acceleration: Option<f64>,
+    last_dt: f64,
+    /// Time passed to [`Simulation::advance`] that has not been stepped through yet.
+    unstepped: f64,
+}
+
+impl Simulation {
+    pub fn new(config: SimulationConfig) -> Self {
+        Self {
+            positions: Vec::new(),
+            velocities: Vec::new(),
+            masses: Vec::new(),
+            radii: Vec::new(),
+            config,
+            time: 0.0,
+            octree: barnes_hut::Octree::default(),
+            integrator: config.integrator.create(),
+            collision_state: collisions::CollisionState::default(),
+            max_acceleration: None,
+            last_dt: 0.0,
+            unstepped: 0.0,
+        }
+    }
+
+    pub fn add_body(&mut self, position: DVec3, velocity: DVec3, mass: f64, radius: f64) {
+        self.positions.push(position);
+        self.velocities.push(velocity);
+        self.masses.push(mass);
+        self.radii.push(radius);
+        self.integrator.reset();
+        self.max_acceleration = None;
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    pub fn positions(&self) -> &[DVec3] {
+        &self.positions
+    }
+
+    pub fn velocities(&self) -> &[DVec3] {
+        &self.velocities
+    }
+
+    pub fn masses(&self) -> &[f64] {
+        &self.masses
+    }
+
+    /// The simulated time elapsed since the simulation was created.
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    /// The length of the most recent step.
+    pub fn last_dt(&self) -> f64 {
+        self.last_dt
+    }
+
+    pub fn config(&self) -> &SimulationConfig {
+        &self.config
+    }
+
+    /// Applies `config` from the next step on.
+    pub fn set_config(&mut self, config: SimulationConfig) {
+        if config.integrator != self.config.integrator {
+            self.integrator = config.integrator.create();
+        } else if config != self.config {
+            self.integrator.reset();
+        }
+        self.config = config;
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            positions: self.positions.clone(),
+            velocities: self.velocities.clone(),
+            masses: self.masses.clone(),
+            radii: self.radii.clone(),
+            time: self.time,
+        }
+    }
+
+    /// Returns the bodies and elapsed time to those of `snapshot`, keeping the current config.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.positions.clone_from(&snapshot.positions);
+        self.velocities.clone_from(&snapshot.velocities);
+        self.masses.clone_from(&snapshot.masses);
+        self.radii.clone_from(&snapshot.radii);
+        self.time = snapshot.time;
+        self.unstepped = 0.0;
+        self.integrator.reset();
+        self.max_acceleration = None;
+    }
+
+    /// The total kinetic plus potential energy of the system.
+    /// Useful for checking how well a step size conserves energy.
+    pub fn total_energy(&self) -> f64 {
+        let kinetic: f64 = self
+            .velocities
+            .iter()
+            .zip(&self.masses)
+            .map(|(v, m)| 0.5 * m * v.length_squared())
+            .sum();
+        let potential = direct::potential_energy(
+            &self.positions,
+            &self.masses,
+            self.config.units.gravitational_constant(),
+            self.config.softening,
+        );
+        kinetic + potential
+    }
+
+    /// Advances the simulation by `duration`, in as many steps as the configured timestep needs.
+    ///
+    /// Stepping stops early after `MAX_STEPS_PER_ADVANCE` steps or `ADVANCE_TIME_LIMIT` of
+    /// wall-clock time, and the time left is dropped. Otherwise a simulation that cannot keep up
+    /// would fall further behind with every call, each taking longer than the last.
+    pub fn advance(&mut self, duration: f64) {
+        if duration <= 0.0 {
+            return;
+        }
+        if let Solver::BarnesHut {
+            tree_budget: Some(budget),
+            ..
+        } = self.config.solver
+        {
+            self.octree.begin_frame(budget);
+        }
+
+        let started = Instant::now();
+        let mut steps = 0;
+        let mut within_limits = || {
+            steps += 1;
+            steps <= MAX_STEPS_PER_ADVANCE && (steps == 1 || started.elapsed() < ADVANCE_TIME_LIMIT)
+        };
+        self.unstepped += duration;
+        match self.config.timestep {
+            Timestep::Fixed { dt } => {
+                // Rounding in the running total must not leave a step a hair short, which would
+                // make frames that should take the same number of steps alternate between counts.
+                while self.unstepped >= dt * (1.0 - 1e-9) {
+                    if !within_limits() {
+                        self.drop_unstepped();
+                        break;
+                    }
+                    self.step(dt);
+                    self.unstepped -= dt;
+                }
+            }
+            Timestep::Adaptive { accuracy, min, max } => loop {
+                // Until forces have been evaluated once, start cautiously with the smallest step.
+                let dt = self
+                    .max_acceleration
+                    .map_or(min, |a| (2.0 * accuracy / a).sqrt().clamp(min, max));
+                // As with fixed steps, time short of a whole step waits for the next advance
+                // rather than being taken as a sliver of a step below `min`.
+                if self.unstepped < dt * (1.0 - 1e-9) {
+                    break;
+                }
+                if !within_limits() {
+                    self.drop_unstepped();
+                    break;
+                }
+                self.step(dt);
+                self.unstepped -= dt;
+            },
+        }
+    }
+
+    fn drop_unstepped(&mut self) {
+        log::debug!(
+            "Simulation falling behind, dropping {} of unstepped time",
+            self.config.units.format_time(self.unstepped)
+        );
+        self.unstepped = 0.0;
+    }
+
+    /// Advances the simulation by a single step of `dt` using the current integrator.
+    pub fn step(&mut self, dt: f64) {
+        let config = &self.config;
+        let octree = &mut self.octree;
+        let masses = &self.masses;
+        let max_acceleration = &mut self.max_acceleration;
+        self.integrator.step(
+            &mut self.positions,
+            &mut self.velocities,
+            dt,
+            &mut |positions, accelerations| {
+                compute_accelerations(config, octree, positions, masses, accelerations);
+                let max = accelerations
+                    .iter()
+                    .map(|a| a.length_squared())
+                    .fold(0.0, f64::max);
+                *max_acceleration = Some(max.sqrt());
+            },
+        );
+
+        if let collisions::Collisions::Bounce { restitution } = self.config.collisions {
+            let _scope = profiler::scope("Collisions");
+            let collided = collisions::bounce(
+                &mut self.collision_state,
+                &mut self.positions,
+                &mut self.velocities,
+                &self.masses,
+                &self.radii,
+                restitution,
+            );
+            // Bounced bodies were moved, invalidating anything the integrator kept.
+            if collided > 0 {
+                self.integrator.reset();
+            }
+        }
+
+        if let Some(boundary) = self.config.boundary {
+            let _scope = profiler::scope("Boundary");
+            let contained = match boundary.walls {
+                boundary::Walls::Reflect => {
+                    boundary.reflect(&mut self.positions, &mut self.velocities)
+                }
+                boundary::Walls::Absorb => {
+                    // Non-finite bodies are never inside, but are left for the watchdog to
+                    // find rather than quietly removed.
+                    let absorbed = self.remove_bodies(|position| {
+                        position.is_finite() && !boundary.contains(position)
+                    });
+                    if absorbed > 0 {
+                        log::debug!("{} bodies absorbed by the boundary", absorbed);
+                    }
+                    absorbed
+                }
+            };
+            // As with collisions, moved or removed bodies invalidate the integrator's state.
+            if contained > 0 {
+                self.integrator.reset();
+            }
+        }
+
+        self.time += dt;
+        self.last_dt = dt;
+    }
+
+    /// Removes every body whose position matches `remove`, returning how many were removed.
+    /// The remaining bodies keep their relative order.
+    fn remove_bodies(&mut self, mut remove: impl FnMut(DVec3) -> bool) -> usize {
+        let mut kept = 0;
+        for body in 0..self.positions.len() {
+            if remove(self.positions[body]) {
+                continue;
+            }
+            self.positions.swap(kept, body);
+            self.velocities.swap(kept, body);
+            self.masses.swap(kept, body);
+            self.radii.swap(kept, body);
+            kept += 1;
+        }
+        let removed = self.positions.len() - kept;
+        self.positions.truncate(kept);
+        self.velocities.truncate(kept);
+        self.masses.truncate(kept);
+        self.radii.truncate(kept);
+        if removed > 0 {
+            self.max_acceleration = None;
+        }
+        removed
+    }
+}
+
+fn compute_accelerations(
+    config: &SimulationConfig,
+    octree: &mut barnes_hut::Octree,
+    positions: &[DVec3],
+    masses: &[f64],
+    accelerations: &mut [DVec3],
+) {
+    match config.solver {
+        Solver::Direct => direct::accelerations(
+            positions,
+            masses,
+            config.units.gravitational_constant(),
+            config.softening,
+            accelerations,
+        ),
+        Solver::BarnesHut { theta, tree_budget } => {
+            if tree_budget.is_some() {
+                let _scope = profiler::scope("Tree update");
+                octree.update(positions, masses);
+            } else {
+                let _scope = profiler::scope("Tree build");
+                octree.build(positions, masses);
+            }
+            let _scope = profiler::scope("Tree walk");
+            octree.accelerations(
+                positions,
+                masses,
+                config.units.gravitational_constant(),
+                config.softening,
+                theta,
+                accelerations,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::DVec3;
+
+    use super::{MAX_STEPS_PER_ADVANCE, Simulation, SimulationConfig, Timestep, boundary};
+
+    fn two_bodies(dt: f64) -> Simulation {
+        let mut sim = Simulation::new(SimulationConfig {
+            timestep: Timestep::Fixed { dt },
+            ..SimulationConfig::default()
+        });
+        sim.add_body(DVec3::ZERO, DVec3::ZERO, 1.0, 0.0);
+        sim.add_body(DVec3::X, DVec3::Y, 0.001, 0.0);
+        sim
+    }
+
+    #[test]
+    fn fixed_steps_carry_leftover_time() {
+        let dt = 0.25;
+        let mut sim = two_bodies(dt);
+        sim.advance(0.6);
+        assert_eq!(sim.time(), 0.5);
+        assert_eq!(sim.last_dt(), dt);
+        sim.advance(0.15);
+        assert_eq!(sim.time(), 0.75);
+        assert_eq!(sim.last_dt(), dt);
+    }
+
+    #[test]
+    fn adaptive_steps_stay_within_bounds_and_carry_leftover_time() {
+        let (min, max) = (0.01, 0.25);
+        let mut sim = two_bodies(min);
+        sim.set_config(SimulationConfig {
+            timestep: Timestep::Adaptive {
+                accuracy: 1.0,
+                min,
+                max,
+            },
+            ..*sim.config()
+        });
+        let mut time = 0.0;
+        // Each frame is a little over the largest step, which used to leave a sliver of a step.
+        for _ in 0..20 {
+            sim.advance(0.255);
+            if sim.time() > time {
+                assert!(
+                    (min..=max).contains(&sim.last_dt()),
+                    "step {}",
+                    sim.last_dt()
+                );
+                time = sim.time();
+            }
+        }
+        // Only less than a step's worth of time is left over.
+        assert!(20.0 * 0.255 - sim.time() < max, "time {}", sim.time());
+    }
+
+    #[test]
+    fn advance_drops_time_it_cannot_keep_up_with() {
+        let dt = 1e-6;
+        let mut sim = two_bodies(dt);
+        sim.advance(1.0);
+        assert!(sim.time() <= MAX_STEPS_PER_ADVANCE as f64 * dt * 1.000001);
+        // The dropped backlog is not made up for on the next advance.
+        let time = sim.time();
+        sim.advance(dt);
+        assert!((sim.time() - time - dt).abs() < dt * 1e-6);
+    }
+
+    #[test]
+    fn absorbing_walls_keep_non_finite_bodies() {
+        let mut sim = Simulation::new(SimulationConfig {
+            boundary: Some(boundary::Boundary {
+                shape: boundary::BoundaryShape::Box { half_size: 10.0 },
+                walls: boundary::Walls::Absorb,
+            }),
+            ..SimulationConfig::default()
+        });
+        sim.add_body(DVec3::ZERO, DVec3::ZERO, 1.0, 0.0);
+        sim.add_body(DVec3::splat(f64::NAN), DVec3::ZERO, 1.0, 0.0);
+        sim.step(0.01);
+        assert_eq!(sim.len(), 2);
+        assert!(sim.positions().iter().any(|position| !position.is_finite()));
+    }
+}
@@ -0,0 +1,465 @@
+use std::time::{Duration, Instant};
+
+use glam::DVec3;
+
+/// Leaves holding more bodies than this are split into eight children.
+const LEAF_CAPACITY: u32 = 8;
+
+/// Bodies closer together than the cell size at this depth share a leaf,
+/// which keeps coincident bodies from subdividing forever.
+const MAX_DEPTH: u32 = 32;
+
+/// Marks the end of a leaf's body list, and leaves' missing children.
+const NONE: u32 = u32::MAX;
+
+/// How many frames of incremental updates may run before the tree is rebuilt from scratch,
+/// clearing out the empty cells and stretched bounds that reinsertion leaves behind.
+/// Counted in frames rather than updates, since integrators with many force evaluations per
+/// step would otherwise rebuild nearly every frame.
+const REBUILD_INTERVAL: u32 = 64;
+
+/// How much larger than the bodies' bounds the root cell is made on a rebuild,
+/// so that bodies can drift for a while before leaving the tree forces another.
+const ROOT_MARGIN: f64 = 1.5;
+
+/// A cell of the octree.
+struct Node {
+    /// The fixed region of space the node subdivides.
+    cell_center: DVec3,
+    cell_half_size: f64,
+    depth: u32,
+    /// The first of the node's eight children, which are stored contiguously, or `NONE` for leaves.
+    first_child: u32,
+    /// For leaves, the first body in the leaf, with the rest linked through `Octree::next_body`.
+    first_body: u32,
+    body_count: u32,
+    /// The bounds, mass and centre of mass of the bodies below the node, refitted on every update.
+    /// Bodies that have drifted out of their cell without being reinserted stretch the bounds
+    /// beyond the cell, which keeps forces correct at the cost of opening more cells.
+    min: DVec3,
+    max: DVec3,
+    mass: f64,
+    center_of_mass: DVec3,
+}
+
+impl Node {
+    fn new(cell_center: DVec3, cell_half_size: f64, depth: u32) -> Self {
+        Self {
+            cell_center,
+            cell_half_size,
+            depth,
+            first_child: NONE,
+            first_body: NONE,
+            body_count: 0,
+            min: DVec3::INFINITY,
+            max: DVec3::NEG_INFINITY,
+            mass: 0.0,
+            center_of_mass: DVec3::ZERO,
+        }
+    }
+
+    fn cell_contains(&self, position: DVec3) -> bool {
+        (position - self.cell_center)
+            .abs()
+            .cmple(DVec3::splat(self.cell_half_size))
+            .all()
+    }
+
+    fn octant(&self, position: DVec3) -> u32 {
+        (position.x >= self.cell_center.x) as u32
+            | ((position.y >= self.cell_center.y) as u32) << 1
+            | ((position.z >= self.cell_center.z) as u32) << 2
+    }
+}
+
+/// An octree over the bodies' positions, storing the mass and centre of mass of every cell
+/// so that distant groups of bodies can be treated as one.
+///
+/// The tree can be rebuilt from scratch every step with [`Octree::build`], or maintained
+/// incrementally with [`Octree::update`], which only reinserts bodies that have left their
+/// cell and spreads that work over a per-frame time budget.
+#[derive(Default)]
+pub struct Octree {
+    nodes: Vec<Node>,
+    /// The body after each body in its leaf's list.
+    next_body: Vec<u32>,
+    /// The leaf each body is stored in.
+    leaf_of: Vec<u32>,
+    /// Bodies found outside their leaf's cell during an update.
+    strays: Vec<u32>,
+    frames_since_rebuild: u32,
+    /// Reinsertion time left in the current frame, shared by all of the frame's updates.
+    budget_left: Duration,
+}
+
+impl Octree {
+    /// Rebuilds the tree for the given bodies, reusing the previous tree's allocations.
+    pub fn build(&mut self, positions: &[DVec3], masses: &[f64]) {
+        self.nodes.clear();
+        self.next_body.clear();
+        self.next_body.resize(positions.len(), NONE);
+        self.leaf_of.clear();
+        self.leaf_of.resize(positions.len(), 0);
+        self.frames_since_rebuild = 0;
+        if positions.is_empty() {
+            return;
+        }
+
+        let (min, max) = positions
+            .iter()
+            .fold((positions[0], positions[0]), |(min, max), p| {
+                (min.min(*p), max.max(*p))
+            });
+        let half_size = (max - min).max_element().max(f64::MIN_POSITIVE) * 0.5 * ROOT_MARGIN;
+        self.nodes.push(Node::new((min + max) * 0.5, half_size, 0));
+
+        for body in 0..positions.len() as u32 {
+            self.insert(body, positions);
+        }
+        self.refit(positions, masses);
+    }
+
+    /// Starts a new frame of incremental updates, which may spend up to `budget` reinserting bodies.
+    pub fn begin_frame(&mut self, budget: Duration) {
+        self.budget_left = budget;
+        self.frames_since_rebuild += 1;
+    }
+
+    /// Brings the tree up to date with the bodies' new positions.
+    ///
+    /// Bodies that have left their leaf's cell are reinserted until the frame's budget runs out;
+    /// the rest stay where they are until a later update, with the refitted bounds keeping forces
+    /// correct in the meantime. The tree is rebuilt instead when bodies are added or removed,
+    /// when a body leaves the root cell, and on the first update once `REBUILD_INTERVAL` frames
+    /// have begun since the last rebuild.
+    pub fn update(&mut self, positions: &[DVec3], masses: &[f64]) {
+        if self.nodes.is_empty()
+            || self.leaf_of.len() != positions.len()
+            || self.frames_since_rebuild >= REBUILD_INTERVAL
+        {
+            self.build(positions, masses);
+            return;
+        }
+
+        self.strays.clear();
+        for (body, position) in positions.iter().enumerate() {
+            if !self.nodes[self.leaf_of[body] as usize].cell_contains(*position) {
+                if !self.nodes[0].cell_contains(*position) {
+                    self.build(positions, masses);
+                    return;
+                }
+                self.strays.push(body as u32);
+            }
+        }
+
+        // Only time spent reinserting counts against the budget, so that force evaluations
+        // between updates don't use it up before the frame's later steps.
+        let started = Instant::now();
+        let mut reinserted = 0;
+        while reinserted < self.strays.len() && started.elapsed() < self.budget_left {
+            let body = self.strays[reinserted];
+            self.remove(body);
+            self.insert(body, positions);
+            reinserted += 1;
+        }
+        self.budget_left = self.budget_left.saturating_sub(started.elapsed());
+        if reinserted < self.strays.len() {
+            log::trace!(
+                "Tree update budget exhausted, {} of {} bodies left to reinsert",
+                self.strays.len() - reinserted,
+                self.strays.len()
+            );
+        }
+
+        self.refit(positions, masses);
+    }
+
+    /// Adds `body` to the leaf whose cell contains it, splitting the leaf if it overflows.
+    fn insert(&mut self, body: u32, positions: &[DVec3]) {
+        let position = positions[body as usize];
+        let mut index = 0;
+        while self.nodes[index].first_child != NONE {
+            index = (self.nodes[index].first_child + self.nodes[index].octant(position)) as usize;
+        }
+        self.push_body(index, body);
+
+        let leaf = &self.nodes[index];
+        if leaf.body_count > LEAF_CAPACITY && leaf.depth < MAX_DEPTH {
+            self.split(index, positions);
+        }
+    }
+
+    fn push_body(&mut self, leaf: usize, body: u32) {
+        let node = &mut self.nodes[leaf];
+        self.next_body[body as usize] = node.first_body;
+        node.first_body = body;
+        node.body_count += 1;
+        self.leaf_of[body as usize] = leaf as u32;
+    }
+
+    fn remove(&mut self, body: u32) {
+        let leaf = self.leaf_of[body as usize] as usize;
+        let next = self.next_body[body as usize];
+        if self.nodes[leaf].first_body == body {
+            self.nodes[leaf].first_body = next;
+        } else {
+            let mut previous = self.nodes[leaf].first_body;
+            while self.next_body[previous as usize] != body {
+                previous = self.next_body[previous as usize];
+            }
+            self.next_body[previous as usize] = next;
+        }
+        self.nodes[leaf].body_count -= 1;
+    }
+
+    /// Turns a leaf into an internal node, moving its bodies into eight new children.
+    fn split(&mut self, index: usize, positions: &[DVec3]) {
+        let first_child = self.nodes.len();
+        let Node {
+            cell_center,
+            cell_half_size,
+            depth,
+            first_body,
+            ..
+        } = self.nodes[index];
+        for child in 0..8 {
+            let offset = DVec3::new(
+                if child & 1 != 0 { 0.5 } else { -0.5 },
+                if child & 2 != 0 { 0.5 } else { -0.5 },
+                if child & 4 != 0 { 0.5 } else { -0.5 },
+            );
+            self.nodes.push(Node::new(
+                cell_center + offset * cell_half_size,
+                cell_half_size * 0.5,
+                depth + 1,
+            ));
+        }
+
+        let node = &mut self.nodes[index];
+        node.first_child = first_child as u32;
+        node.first_body = NONE;
+        node.body_count = 0;
+
+        let mut body = first_body;
+        while body != NONE {
+            let next = self.next_body[body as usize];
+            let child = first_child + self.nodes[index].octant(positions[body as usize]) as usize;
+            self.push_body(child, body);
+            body = next;
+        }
+
+        for child in first_child..first_child + 8 {
+            let node = &self.nodes[child];
+            if node.body_count > LEAF_CAPACITY && node.depth < MAX_DEPTH {
+                self.split(child, positions);
+            }
+        }
+    }
+
+    /// Recomputes every node's bounds, mass and centre of mass from the bodies below it.
+    fn refit(&mut self, positions: &[DVec3], masses: &[f64]) {
+        // Children are always stored after their parent, so a reverse pass visits them first.
+        for index in (0..self.nodes.len()).rev() {
+            let mut min = DVec3::INFINITY;
+            let mut max = DVec3::NEG_INFINITY;
+            let mut mass = 0.0;
+            let mut weighted_position = DVec3::ZERO;
+
+            let node = &self.nodes[index];
+            if node.first_child == NONE {
+                let mut body = node.first_body;
+                while body != NONE {
+                    let position = positions[body as usize];
+                    min = min.min(position);
+                    max = max.max(position);
+                    mass += masses[body as usize];
+                    weighted_position += position * masses[body as usize];
+                    body = self.next_body[body as usize];
+                }
+            } else {
+                let first_child = node.first_child as usize;
+                for child in &self.nodes[first_child..first_child + 8] {
+                    min = min.min(child.min);
+                    max = max.max(child.max);
+                    mass += child.mass;
+                    weighted_position += child.center_of_mass * child.mass;
+                }
+            }
+
+            let node = &mut self.nodes[index];
+            node.min = min;
+            node.max = max;
+            node.mass = mass;
+            node.center_of_mass = if mass > 0.0 {
+                weighted_position / mass
+            } else {
+                node.cell_center
+            };
+        }
+    }
+
+    /// Computes the gravitational acceleration on every body, O(N log N).
+    ///
+    /// A cell whose width divided by its distance from a body is below `theta` is treated as a
+    /// single mass at its centre of mass; otherwise its children are visited. A `theta` of zero
+    /// visits every body, matching direct summation. Cells containing the body itself are always
+    /// opened. Forces use the same Plummer `softening` as direct summation, and coincident
+    /// bodies exert no force on each other.
+    ///
+    /// The tree must have been built or updated from the same positions and masses.
+    pub fn accelerations(
+        &self,
+        positions: &[DVec3],
+        masses: &[f64],
+        gravitational_constant: f64,
+        softening: f64,
+        theta: f64,
+        accelerations: &mut [DVec3],
+    ) {
+        let theta_squared = theta * theta;
+        let softening_squared = softening * softening;
+        let mut stack = Vec::new();
+
+        for (i, acceleration) in accelerations.iter_mut().enumerate() {
+            let position = positions[i];
+            let mut total = DVec3::ZERO;
+            stack.clear();
+            if !self.nodes.is_empty() {
+                stack.push(0);
+            }
+
+            while let Some(index) = stack.pop() {
+                let node = &self.nodes[index];
+                if node.mass == 0.0 {
+                    continue;
+                }
+                let offset = node.center_of_mass - position;
+                let distance_squared = offset.length_squared();
+                let width = (node.max - node.min).max_element();
+                let contains_body =
+                    position.cmpge(node.min).all() && position.cmple(node.max).all();
+
+                if !contains_body && width * width < theta_squared * distance_squared {
+                    let distance_squared = distance_squared + softening_squared;
+                    total += offset * (node.mass / (distance_squared * distance_squared.sqrt()));
+                } else if node.first_child == NONE {
+                    let mut body = node.first_body;
+                    while body != NONE {
+                        let offset = positions[body as usize] - position;
+                        let distance_squared = offset.length_squared() + softening_squared;
+                        if distance_squared > 0.0 {
+                            total += offset
+                                * (masses[body as usize]
+                                    / (distance_squared * distance_squared.sqrt()));
+                        }
+                        body = self.next_body[body as usize];
+                    }
+                } else {
+                    let first_child = node.first_child as usize;
+                    stack.extend(first_child..first_child + 8);
+                }
+            }
+
+            *acceleration = total * gravitational_constant;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use glam::DVec3;
+
+    use super::Octree;
+    use crate::gravsim::sim::direct;
+
+    /// Bodies scattered through a cube, with some in tight clumps to force deep subdivision,
+    /// from a fixed xorshift sequence.
+    fn bodies(count: usize) -> (Vec<DVec3>, Vec<f64>) {
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 11) as f64 / (1u64 << 53) as f64
+        };
+        let positions = (0..count)
+            .map(|i| {
+                let scale = if i % 10 == 0 { 1e-3 } else { 10.0 };
+                DVec3::new(next(), next(), next()) * scale
+            })
+            .collect();
+        let masses = (0..count).map(|_| 0.5 + next()).collect();
+        (positions, masses)
+    }
+
+    fn max_relative_error(octree: &Octree, positions: &[DVec3], masses: &[f64]) -> f64 {
+        let mut expected = vec![DVec3::ZERO; positions.len()];
+        direct::accelerations(positions, masses, 1.0, 0.01, &mut expected);
+        let mut actual = vec![DVec3::ZERO; positions.len()];
+        octree.accelerations(positions, masses, 1.0, 0.01, 0.0, &mut actual);
+        expected
+            .iter()
+            .zip(&actual)
+            .map(|(expected, actual)| (*expected - *actual).length() / expected.length())
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn zero_theta_matches_direct_summation() {
+        let (positions, masses) = bodies(2000);
+        let mut octree = Octree::default();
+        octree.build(&positions, &masses);
+        let error = max_relative_error(&octree, &positions, &masses);
+        assert!(error < 1e-10, "relative error {}", error);
+    }
+
+    #[test]
+    fn time_between_updates_does_not_use_up_the_budget() {
+        let (mut positions, masses) = bodies(2000);
+        let mut octree = Octree::default();
+        octree.build(&positions, &masses);
+        for (i, position) in positions.iter_mut().enumerate() {
+            *position += DVec3::new(0.37, -0.21, 0.05) * (i % 7) as f64;
+        }
+
+        // As when the frame's earlier steps spend longer walking the tree than the budget.
+        octree.begin_frame(Duration::from_millis(500));
+        std::thread::sleep(Duration::from_millis(600));
+        octree.update(&positions, &masses);
+        assert!(positions.iter().enumerate().all(|(body, position)| {
+            octree.nodes[octree.leaf_of[body] as usize].cell_contains(*position)
+        }));
+    }
+
+    #[test]
+    fn incremental_updates_match_direct_summation() {
+        let (mut positions, masses) = bodies(2000);
+        let mut octree = Octree::default();
+        octree.build(&positions, &masses);
+
+        // Move every body, some far enough to leave their leaf, and leave some of the strays
+        // unreinserted by giving the frame no budget.
+        for (i, position) in positions.iter_mut().enumerate() {
+            *position += DVec3::new(0.37, -0.21, 0.05) * (i % 7) as f64;
+        }
+        octree.begin_frame(Duration::ZERO);
+        octree.update(&positions, &masses);
+        let error = max_relative_error(&octree, &positions, &masses);
+        assert!(
+            error < 1e-10,
+            "relative error after a starved update {}",
+            error
+        );
+
+        octree.begin_frame(Duration::from_secs(10));
+        octree.update(&positions, &masses);
+        let error = max_relative_error(&octree, &positions, &masses);
+        assert!(
+            error < 1e-10,
+            "relative error after a full update {}",
+            error
+        );
+    }
+}
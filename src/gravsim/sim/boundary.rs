@@ -0,0 +1,183 @@
+use glam::DVec3;
+
+use crate::gravsim::localization::Text;
+
+/// The region a [`Boundary`] keeps bodies inside, centred on the origin.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BoundaryShape {
+    /// An axis-aligned cube extending `half_size` from the origin along each axis.
+    Box {
+        half_size: f64,
+    },
+    Sphere {
+        radius: f64,
+    },
+}
+
+/// What happens to bodies that reach a boundary's walls.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Walls {
+    /// Bodies bounce elastically off the walls.
+    #[default]
+    Reflect,
+    /// Bodies that reach the walls are removed from the simulation.
+    Absorb,
+}
+
+impl Walls {
+    pub const ALL: &[Walls] = &[Walls::Reflect, Walls::Absorb];
+
+    pub fn name(self) -> Text {
+        match self {
+            Walls::Reflect => Text::Reflect,
+            Walls::Absorb => Text::Absorb,
+        }
+    }
+}
+
+/// Walls that keep bodies within a fixed region, so that a system stays bounded indefinitely
+/// instead of slowly evaporating.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Boundary {
+    pub shape: BoundaryShape,
+    pub walls: Walls,
+}
+
+impl Boundary {
+    pub fn contains(&self, position: DVec3) -> bool {
+        match self.shape {
+            BoundaryShape::Box { half_size } => position.abs().max_element() <= half_size,
+            BoundaryShape::Sphere { radius } => position.length_squared() <= radius * radius,
+        }
+    }
+
+    /// Brings every body outside the boundary back in, returning how many were outside.
+    ///
+    /// Positions are mirrored in the wall they crossed, and the outward component of velocity
+    /// reversed, as if the body had bounced off the wall partway through the step. Kinetic energy
+    /// is unchanged. Bodies moving fast enough to cross the whole region in one step are clamped
+    /// to it instead.
+    pub fn reflect(&self, positions: &mut [DVec3], velocities: &mut [DVec3]) -> usize {
+        let mut reflected = 0;
+        for (position, velocity) in positions.iter_mut().zip(velocities) {
+            if self.contains(*position) {
+                continue;
+            }
+            reflected += 1;
+            match self.shape {
+                BoundaryShape::Box { half_size } => {
+                    for axis in 0..3 {
+                        if position[axis].abs() > half_size {
+                            let sign = position[axis].signum();
+                            position[axis] = (sign * 2.0 * half_size - position[axis])
+                                .clamp(-half_size, half_size);
+                            velocity[axis] = -sign * velocity[axis].abs();
+                        }
+                    }
+                }
+                BoundaryShape::Sphere { radius } => {
+                    let distance = position.length();
+                    let normal = *position / distance;
+                    *position = normal * (2.0 * radius - distance).clamp(0.0, radius);
+                    let outward_speed = velocity.dot(normal);
+                    if outward_speed > 0.0 {
+                        *velocity -= normal * (2.0 * outward_speed);
+                    }
+                }
+            }
+        }
+        reflected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::DVec3;
+
+    use super::{Boundary, BoundaryShape, Walls};
+
+    /// Bodies just outside the walls, each moving further out.
+    fn escaping_bodies() -> (Vec<DVec3>, Vec<DVec3>) {
+        let positions = vec![
+            DVec3::new(1.1, 0.2, -0.3),
+            DVec3::new(-0.4, -1.05, 0.5),
+            DVec3::new(0.9, 0.95, 1.2),
+            DVec3::new(-1.2, 0.0, 0.0),
+        ];
+        let velocities = vec![
+            DVec3::new(2.0, 0.5, 0.1),
+            DVec3::new(0.3, -1.0, 0.0),
+            DVec3::new(1.0, 1.0, 1.0),
+            DVec3::new(-0.5, 0.7, -0.2),
+        ];
+        (positions, velocities)
+    }
+
+    #[test]
+    fn box_walls_reflect_bodies_back_inside() {
+        let boundary = Boundary {
+            shape: BoundaryShape::Box { half_size: 1.0 },
+            walls: Walls::Reflect,
+        };
+        let (before, velocities_before) = escaping_bodies();
+        let (mut positions, mut velocities) = (before.clone(), velocities_before.clone());
+        assert_eq!(
+            boundary.reflect(&mut positions, &mut velocities),
+            before.len()
+        );
+
+        for i in 0..before.len() {
+            assert!(boundary.contains(positions[i]), "{:?}", positions[i]);
+            assert!((velocities[i].length() - velocities_before[i].length()).abs() < 1e-12);
+            for axis in 0..3 {
+                let expected = if before[i][axis].abs() > 1.0 {
+                    -velocities_before[i][axis]
+                } else {
+                    velocities_before[i][axis]
+                };
+                assert_eq!(velocities[i][axis], expected, "body {} axis {}", i, axis);
+            }
+        }
+    }
+
+    #[test]
+    fn sphere_walls_reflect_bodies_back_inside() {
+        let boundary = Boundary {
+            shape: BoundaryShape::Sphere { radius: 1.0 },
+            walls: Walls::Reflect,
+        };
+        let (before, velocities_before) = escaping_bodies();
+        let (mut positions, mut velocities) = (before.clone(), velocities_before.clone());
+        assert_eq!(
+            boundary.reflect(&mut positions, &mut velocities),
+            before.len()
+        );
+
+        for i in 0..before.len() {
+            assert!(boundary.contains(positions[i]), "{:?}", positions[i]);
+            assert!((velocities[i].length() - velocities_before[i].length()).abs() < 1e-12);
+            let normal = before[i].normalize();
+            let outward_before = velocities_before[i].dot(normal);
+            assert!(outward_before > 0.0);
+            assert!((velocities[i].dot(normal) + outward_before).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn bodies_inside_are_left_alone() {
+        for shape in [
+            BoundaryShape::Box { half_size: 1.0 },
+            BoundaryShape::Sphere { radius: 1.0 },
+        ] {
+            let boundary = Boundary {
+                shape,
+                walls: Walls::Reflect,
+            };
+            let mut positions = vec![DVec3::new(0.5, -0.5, 0.1)];
+            let mut velocities = vec![DVec3::new(3.0, 0.0, 0.0)];
+            assert_eq!(boundary.reflect(&mut positions, &mut velocities), 0);
+            assert_eq!(positions[0], DVec3::new(0.5, -0.5, 0.1));
+            assert_eq!(velocities[0], DVec3::new(3.0, 0.0, 0.0));
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use glam::DVec3;
+
+use crate::gravsim::sim::spatial_hash::SpatialHash;
+
+/// How bodies respond when they touch.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Collisions {
+    /// Bodies pass through each other.
+    #[default]
+    None,
+    /// Bodies bounce off each other, keeping `restitution` of their approach speed:
+    /// 1 for perfectly elastic collisions, 0 for bodies that stop dead relative to each other.
+    Bounce { restitution: f64 },
+}
+
+/// Reusable buffers for finding colliding bodies.
+#[derive(Default)]
+pub struct CollisionState {
+    spatial_hash: SpatialHash,
+    pairs: Vec<(usize, usize)>,
+}
+
+/// Resolves every pair of overlapping bodies by bouncing them apart, returning how many
+/// pairs collided.
+///
+/// Touching pairs are found with a spatial hash whose cells fit the largest body, then resolved
+/// one at a time. Bodies approaching each other receive equal and opposite impulses along the
+/// line between their centres, which conserves momentum. Overlapping bodies are also pushed apart
+/// until they just touch, heavier bodies moving less, so that they do not sink into each other.
+pub fn bounce(
+    state: &mut CollisionState,
+    positions: &mut [DVec3],
+    velocities: &mut [DVec3],
+    masses: &[f64],
+    radii: &[f64],
+    restitution: f64,
+) -> usize {
+    let max_radius = radii.iter().copied().fold(0.0, f64::max);
+    if max_radius <= 0.0 {
+        return 0;
+    }
+
+    state.spatial_hash.build(positions, 2.0 * max_radius);
+    state.pairs.clear();
+    for i in 0..positions.len() {
+        state
+            .spatial_hash
+            .for_each_neighbor(positions, positions[i], radii[i] + max_radius, |j| {
+                if j > i {
+                    state.pairs.push((i, j));
+                }
+            });
+    }
+
+    let mut collisions = 0;
+    for &(i, j) in &state.pairs {
+        let offset = positions[j] - positions[i];
+        let contact_distance = radii[i] + radii[j];
+        let distance_squared = offset.length_squared();
+        if distance_squared >= contact_distance * contact_distance || distance_squared == 0.0 {
+            continue;
+        }
+        collisions += 1;
+
+        let distance = distance_squared.sqrt();
+        let normal = offset / distance;
+        let inverse_masses = [1.0 / masses[i], 1.0 / masses[j]];
+        let inverse_mass_sum = inverse_masses[0] + inverse_masses[1];
+
+        let approach_speed = (velocities[j] - velocities[i]).dot(normal);
+        if approach_speed < 0.0 {
+            let impulse = -(1.0 + restitution) * approach_speed / inverse_mass_sum;
+            velocities[i] -= normal * (impulse * inverse_masses[0]);
+            velocities[j] += normal * (impulse * inverse_masses[1]);
+        }
+
+        let correction = normal * ((contact_distance - distance) / inverse_mass_sum);
+        positions[i] -= correction * inverse_masses[0];
+        positions[j] += correction * inverse_masses[1];
+    }
+    collisions
+}
@@ -0,0 +1,74 @@
+use glam::DVec3;
+
+/// Computes the gravitational acceleration on every body by summing over all pairs, O(N²).
+///
+/// Each pair is visited once and its contribution applied to both bodies.
+/// Forces use Plummer softening, behaving as if each body were spread over a radius of about
+/// `softening` so that close passes stay finite. Coincident bodies exert no force on each other.
+pub fn accelerations(
+    positions: &[DVec3],
+    masses: &[f64],
+    gravitational_constant: f64,
+    softening: f64,
+    accelerations: &mut [DVec3],
+) {
+    let softening_squared = softening * softening;
+    accelerations.fill(DVec3::ZERO);
+
+    for i in 0..positions.len() {
+        for j in (i + 1)..positions.len() {
+            let offset = positions[j] - positions[i];
+            let distance_squared = offset.length_squared() + softening_squared;
+            if distance_squared == 0.0 {
+                continue;
+            }
+            let inverse_cube = 1.0 / (distance_squared * distance_squared.sqrt());
+            let direction = offset * (gravitational_constant * inverse_cube);
+            accelerations[i] += direction * masses[j];
+            accelerations[j] -= direction * masses[i];
+        }
+    }
+}
+
+/// Computes the total gravitational potential energy of the system by summing over all pairs,
+/// with the same softening as [`accelerations`].
+pub fn potential_energy(
+    positions: &[DVec3],
+    masses: &[f64],
+    gravitational_constant: f64,
+    softening: f64,
+) -> f64 {
+    let softening_squared = softening * softening;
+    let mut energy = 0.0;
+    for i in 0..positions.len() {
+        for j in (i + 1)..positions.len() {
+            let distance = (positions[i].distance_squared(positions[j]) + softening_squared).sqrt();
+            if distance > 0.0 {
+                energy -= gravitational_constant * masses[i] * masses[j] / distance;
+            }
+        }
+    }
+    energy
+}
+
+/// The magnitude of the gravitational force between bodies `i` and `j`, and their potential energy,
+/// with the same softening as [`accelerations`].
+pub fn pair_interaction(
+    positions: &[DVec3],
+    masses: &[f64],
+    gravitational_constant: f64,
+    softening: f64,
+    i: usize,
+    j: usize,
+) -> (f64, f64) {
+    let distance_squared = positions[i].distance_squared(positions[j]) + softening * softening;
+    if distance_squared == 0.0 {
+        return (0.0, 0.0);
+    }
+    let distance = distance_squared.sqrt();
+    let force =
+        gravitational_constant * masses[i] * masses[j] * positions[i].distance(positions[j])
+            / (distance_squared * distance);
+    let energy = -gravitational_constant * masses[i] * masses[j] / distance;
+    (force, energy)
+}
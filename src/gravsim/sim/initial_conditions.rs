@@ -0,0 +1,533 @@
+use glam::{DQuat, DVec3};
+
+use crate::gravsim::sim::{Simulation, SimulationConfig, barnes_hut::Octree};
+
+/// A small, fast generator for reproducible initial conditions. SplitMix64, which is more than
+/// random enough for sampling positions, and keeps a given seed producing the same system.
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A uniform sample from `[0, 1)`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// A uniformly distributed unit vector.
+    fn unit_vector(&mut self) -> DVec3 {
+        let z = 2.0 * self.next_f64() - 1.0;
+        let (sin, cos) = (std::f64::consts::TAU * self.next_f64()).sin_cos();
+        let r = (1.0 - z * z).sqrt();
+        DVec3::new(r * cos, r * sin, z)
+    }
+}
+
+/// The largest fraction of a Plummer sphere's mass that a radius is sampled for, truncating the
+/// sphere at about 39 scale radii. The untruncated profile puts about one body in 7000 beyond
+/// 100 scale radii, and the rare sample very close to 1 arbitrarily far out.
+const PLUMMER_MAX_MASS_FRACTION: f64 = 0.999;
+
+/// Samples the positions and velocities of `count` bodies forming a Plummer sphere with the given
+/// total mass and scale radius, following Aarseth, Hénon & Wielen (1974): radii by inverting the
+/// cumulative mass profile, and speeds by rejection sampling the isotropic distribution function,
+/// so that the sphere is in virial equilibrium under its own gravity. The sphere is centred on the
+/// origin and at rest as a whole.
+fn sample_plummer(
+    rng: &mut Rng,
+    count: usize,
+    total_mass: f64,
+    scale_radius: f64,
+    gravitational_constant: f64,
+) -> Vec<(DVec3, DVec3)> {
+    let mut bodies: Vec<_> = (0..count)
+        .map(|_| {
+            // Inverting M(r)/M = r³/(r² + a²)^(3/2), resampling the one value it is undefined for
+            // and those beyond the truncation.
+            let radius = loop {
+                let fraction = rng.next_f64();
+                if fraction > 0.0 && fraction < PLUMMER_MAX_MASS_FRACTION {
+                    break scale_radius / (fraction.powf(-2.0 / 3.0) - 1.0).sqrt();
+                }
+            };
+
+            // The speed as a fraction q of the local escape speed follows q²(1 - q²)^(7/2),
+            // whose maximum is just under 0.1.
+            let q = loop {
+                let q = rng.next_f64();
+                if 0.1 * rng.next_f64() < q * q * (1.0 - q * q).powf(3.5) {
+                    break q;
+                }
+            };
+            let escape_speed = (2.0 * gravitational_constant * total_mass
+                / (radius * radius + scale_radius * scale_radius).sqrt())
+            .sqrt();
+
+            (
+                rng.unit_vector() * radius,
+                rng.unit_vector() * (q * escape_speed),
+            )
+        })
+        .collect();
+
+    let count = bodies.len().max(1) as f64;
+    let center = bodies.iter().map(|(p, _)| *p).sum::<DVec3>() / count;
+    let drift = bodies.iter().map(|(_, v)| *v).sum::<DVec3>() / count;
+    for (position, velocity) in &mut bodies {
+        *position -= center;
+        *velocity -= drift;
+    }
+    bodies
+}
+
+/// Shifts `bodies`, given as positions, velocities and masses, so that their centre of mass is at
+/// the origin and at rest.
+fn center(bodies: &mut [(DVec3, DVec3, f64)]) {
+    let total_mass = bodies.iter().map(|(_, _, m)| m).sum::<f64>();
+    if total_mass <= 0.0 {
+        return;
+    }
+    let center = bodies.iter().map(|(p, _, m)| *p * *m).sum::<DVec3>() / total_mass;
+    let drift = bodies.iter().map(|(_, v, m)| *v * *m).sum::<DVec3>() / total_mass;
+    for (position, velocity, _) in bodies {
+        *position -= center;
+        *velocity -= drift;
+    }
+}
+
+/// Adds `bodies`, given as positions, velocities and masses, to `sim`.
+fn add_bodies(sim: &mut Simulation, bodies: &[(DVec3, DVec3, f64)]) {
+    for (position, velocity, mass) in bodies {
+        sim.add_body(*position, *velocity, *mass, 0.0);
+    }
+}
+
+/// A Plummer sphere: a spherical, self-gravitating cluster in equilibrium, whose density falls off
+/// as `(1 + r²/a²)^(-5/2)` with scale radius `a`.
+#[derive(Clone, Copy, Debug)]
+pub struct Plummer {
+    pub count: usize,
+    pub total_mass: f64,
+    pub scale_radius: f64,
+    pub seed: u64,
+}
+
+impl Plummer {
+    /// Adds the cluster's bodies to `sim`, centred on the origin and at rest as a whole.
+    ///
+    /// Bodies share the mass equally, and start in virial equilibrium.
+    /// Bodies are given no radius, so they never collide.
+    pub fn generate(&self, sim: &mut Simulation) {
+        let mut rng = Rng(self.seed);
+        let mass = self.total_mass / self.count.max(1) as f64;
+        let mut bodies: Vec<_> = sample_plummer(
+            &mut rng,
+            self.count,
+            self.total_mass,
+            self.scale_radius,
+            sim.config().units.gravitational_constant(),
+        )
+        .into_iter()
+        .map(|(position, velocity)| (position, velocity, mass))
+        .collect();
+        center(&mut bodies);
+        add_bodies(sim, &bodies);
+    }
+}
+
+/// A spherical component of a [`DiscGalaxy`], with a Plummer density profile.
+#[derive(Clone, Copy, Debug)]
+pub struct Spheroid {
+    pub count: usize,
+    pub mass: f64,
+    pub scale_radius: f64,
+}
+
+/// A rotating disc galaxy: an exponential disc around a central bulge, optionally embedded in a
+/// dark matter halo.
+///
+/// The disc's surface density falls off as `exp(-R/R_d)` with scale length `R_d`, and its density
+/// away from the midplane as `sech²(z/z_0)` with scale height `z_0`. The disc rotates
+/// anticlockwise about the z axis.
+#[derive(Clone, Copy, Debug)]
+pub struct DiscGalaxy {
+    pub disc_count: usize,
+    pub disc_mass: f64,
+    pub scale_length: f64,
+    pub scale_height: f64,
+    pub bulge: Spheroid,
+    pub halo: Option<Spheroid>,
+    pub seed: u64,
+}
+
+impl DiscGalaxy {
+    /// The Barnes-Hut opening angle used to find the accelerations the disc's orbits are balanced
+    /// against. Tighter than the simulation usually needs, since errors here become eccentricities.
+    const THETA: f64 = 0.3;
+
+    /// Adds the galaxy's bodies to `sim`, centred on the origin and at rest as a whole.
+    ///
+    /// Disc bodies are put on circular orbits, their speed balancing the inward pull of every
+    /// body in the galaxy, computed with the simulation's softening so that the disc starts out
+    /// in equilibrium in the simulated potential. The bulge and halo are sampled as Plummer
+    /// spheres, their speeds scaled up for the rest of the galaxy's pull, so they settle a little
+    /// at first. Being cold, a massive disc is unstable, and soon grows spiral arms and clumps.
+    /// Bodies are given no radius, so they never collide.
+    pub fn generate(&self, sim: &mut Simulation) {
+        let bodies = self.bodies(sim.config());
+        add_bodies(sim, &bodies);
+    }
+
+    /// Samples the galaxy's bodies as positions, velocities and masses, centred on the origin.
+    fn bodies(&self, config: &SimulationConfig) -> Vec<(DVec3, DVec3, f64)> {
+        let mut rng = Rng(self.seed);
+        let gravitational_constant = config.units.gravitational_constant();
+        let mut bodies = Vec::new();
+
+        let disc_body_mass = self.disc_mass / self.disc_count.max(1) as f64;
+        for _ in 0..self.disc_count {
+            // The cumulative mass 1 - (1 + x)e^(-x) of an exponential disc is that of the sum of
+            // two exponentially distributed variables, and the inverse of sech² is atanh, which is
+            // resampled at the one value it is infinite for.
+            let radius =
+                -self.scale_length * ((1.0 - rng.next_f64()) * (1.0 - rng.next_f64())).ln();
+            let height = loop {
+                let fraction = rng.next_f64();
+                if fraction > 0.0 {
+                    break self.scale_height * (2.0 * fraction - 1.0).atanh();
+                }
+            };
+            let (sin, cos) = (std::f64::consts::TAU * rng.next_f64()).sin_cos();
+            bodies.push((
+                DVec3::new(cos * radius, sin * radius, height),
+                DVec3::ZERO,
+                disc_body_mass,
+            ));
+        }
+
+        // The disc is centred before its orbits are balanced about the origin, since centring it
+        // afterwards would leave them off-centre.
+        center(&mut bodies);
+
+        for spheroid in std::iter::once(&self.bulge).chain(&self.halo) {
+            let mass = spheroid.mass / spheroid.count.max(1) as f64;
+            bodies.extend(
+                sample_plummer(
+                    &mut rng,
+                    spheroid.count,
+                    spheroid.mass,
+                    spheroid.scale_radius,
+                    gravitational_constant,
+                )
+                .into_iter()
+                .map(|(position, velocity)| (position, velocity, mass)),
+            );
+        }
+
+        let positions: Vec<_> = bodies.iter().map(|(p, _, _)| *p).collect();
+        let masses: Vec<_> = bodies.iter().map(|(_, _, m)| *m).collect();
+        let mut accelerations = vec![DVec3::ZERO; bodies.len()];
+        let mut octree = Octree::default();
+        octree.build(&positions, &masses);
+        octree.accelerations(
+            &positions,
+            &masses,
+            gravitational_constant,
+            config.softening,
+            Self::THETA,
+            &mut accelerations,
+        );
+
+        let (disc, spheroids) = bodies.split_at_mut(self.disc_count);
+        for ((position, velocity, _), acceleration) in disc.iter_mut().zip(&accelerations) {
+            let radius = position.truncate().length();
+            if radius == 0.0 {
+                continue;
+            }
+            let outward = DVec3::new(position.x, position.y, 0.0) / radius;
+            let speed = (-radius * acceleration.dot(outward)).max(0.0).sqrt();
+            *velocity = DVec3::Z.cross(outward) * speed;
+        }
+
+        // A spheroid's bodies move too slowly for the extra pull of the rest of the galaxy, so
+        // their speeds are scaled by how much faster the local circular speed is.
+        let mut spheroid_bodies = spheroids.iter_mut().zip(&accelerations[self.disc_count..]);
+        for spheroid in std::iter::once(&self.bulge).chain(&self.halo) {
+            let scale_radius_squared = spheroid.scale_radius * spheroid.scale_radius;
+            for ((position, velocity, _), acceleration) in
+                spheroid_bodies.by_ref().take(spheroid.count)
+            {
+                // Squared circular speeds, under the spheroid's own gravity and the whole galaxy's.
+                let radius_squared = position.length_squared();
+                let own = gravitational_constant * spheroid.mass * radius_squared
+                    / (radius_squared + scale_radius_squared).powf(1.5);
+                let total = -position.dot(*acceleration);
+                if own > 0.0 && total > own {
+                    *velocity *= (total / own).sqrt();
+                }
+            }
+        }
+        // The galaxy is brought to rest by the spheroids alone, as taking its net momentum out
+        // of every body would add a drift to the disc's balanced orbits.
+        let spheroid_mass = spheroids.iter().map(|(_, _, m)| m).sum::<f64>();
+        if spheroid_mass > 0.0 {
+            let momentum = disc
+                .iter()
+                .chain(spheroids.iter())
+                .map(|(_, v, m)| *v * *m)
+                .sum::<DVec3>();
+            for (_, velocity, _) in spheroids.iter_mut() {
+                *velocity -= momentum / spheroid_mass;
+            }
+        }
+
+        center(&mut bodies);
+        bodies
+    }
+}
+
+/// Two identical disc galaxies on a collision course, the second tilted out of the first's plane.
+///
+/// The galaxies start `separation` apart along the x axis, approaching each other at
+/// `relative_speed`, with their paths offset by `impact_parameter` along the y axis.
+/// A head-on collision has no impact parameter; larger ones give grazing encounters whose
+/// tidal tails are more pronounced.
+#[derive(Clone, Copy, Debug)]
+pub struct GalaxyCollision {
+    pub separation: f64,
+    pub impact_parameter: f64,
+    pub relative_speed: f64,
+    /// The angle, in radians, the second galaxy's disc is rotated by about the x axis.
+    pub inclination: f64,
+}
+
+impl GalaxyCollision {
+    /// Adds both galaxies' bodies to `sim`, with their combined centre of mass at the origin and
+    /// at rest. The second galaxy is sampled with the next seed, so the two are not mirror images.
+    pub fn generate(&self, galaxy: &DiscGalaxy, sim: &mut Simulation) {
+        let tilt = DQuat::from_rotation_x(self.inclination);
+        let offset = DVec3::new(self.separation, self.impact_parameter, 0.0) * 0.5;
+        let velocity = DVec3::new(-self.relative_speed, 0.0, 0.0) * 0.5;
+
+        let mut bodies = galaxy.bodies(sim.config());
+        for (position, body_velocity, _) in &mut bodies {
+            *position -= offset;
+            *body_velocity -= velocity;
+        }
+        let second = DiscGalaxy {
+            seed: galaxy.seed.wrapping_add(1),
+            ..*galaxy
+        };
+        bodies.extend(second.bodies(sim.config()).into_iter().map(
+            |(position, body_velocity, mass)| {
+                (
+                    tilt * position + offset,
+                    tilt * body_velocity + velocity,
+                    mass,
+                )
+            },
+        ));
+        center(&mut bodies);
+        add_bodies(sim, &bodies);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::DVec3;
+
+    use super::{DiscGalaxy, GalaxyCollision, Plummer, Spheroid};
+    use crate::gravsim::sim::{Simulation, SimulationConfig, direct};
+
+    /// The total kinetic and (unsoftened) potential energy of `sim`'s bodies, with G = 1.
+    fn energies(sim: &Simulation) -> (f64, f64) {
+        let (positions, velocities, masses) = (sim.positions(), sim.velocities(), sim.masses());
+        let kinetic = velocities
+            .iter()
+            .zip(masses)
+            .map(|(v, m)| 0.5 * m * v.length_squared())
+            .sum();
+        let mut potential = 0.0;
+        for i in 0..positions.len() {
+            for j in 0..i {
+                potential -= masses[i] * masses[j] / positions[i].distance(positions[j]);
+            }
+        }
+        (kinetic, potential)
+    }
+
+    #[test]
+    fn plummer_sphere_is_in_virial_equilibrium() {
+        let plummer = Plummer {
+            count: 4000,
+            total_mass: 10.0,
+            scale_radius: 2.0,
+            seed: 7,
+        };
+        let mut sim = Simulation::new(SimulationConfig::default());
+        plummer.generate(&mut sim);
+        assert_eq!(sim.len(), plummer.count);
+
+        let (kinetic, potential) = energies(&sim);
+        let virial_ratio = 2.0 * kinetic / -potential;
+        let mut radii: Vec<f64> = sim.positions().iter().map(|p| p.length()).collect();
+        radii.sort_by(f64::total_cmp);
+        let half_mass_radius = radii[radii.len() / 2] / plummer.scale_radius;
+        assert!(
+            (virial_ratio - 1.0).abs() < 0.05,
+            "2K/|W| = {}",
+            virial_ratio
+        );
+        // The half-mass radius of a Plummer sphere is (2^(2/3) - 1)^(-1/2) ≈ 1.305 scale radii.
+        assert!(
+            (half_mass_radius - 1.305).abs() < 0.05,
+            "half-mass radius {} scale radii",
+            half_mass_radius
+        );
+        let max_radius = radii[radii.len() - 1] / plummer.scale_radius;
+        assert!(
+            max_radius < 39.0,
+            "outermost body at {} scale radii",
+            max_radius
+        );
+    }
+
+    fn galaxy() -> DiscGalaxy {
+        DiscGalaxy {
+            disc_count: 1500,
+            disc_mass: 100.0,
+            scale_length: 2.0,
+            scale_height: 0.1,
+            bulge: Spheroid {
+                count: 300,
+                mass: 25.0,
+                scale_radius: 0.4,
+            },
+            halo: Some(Spheroid {
+                count: 500,
+                mass: 300.0,
+                scale_radius: 10.0,
+            }),
+            seed: 3,
+        }
+    }
+
+    /// The total mass, centre of mass and total momentum of `sim`'s bodies.
+    fn totals(sim: &Simulation) -> (f64, DVec3, DVec3) {
+        let masses = sim.masses();
+        let total_mass: f64 = masses.iter().sum();
+        let center = sim
+            .positions()
+            .iter()
+            .zip(masses)
+            .map(|(p, m)| *p * *m)
+            .sum::<DVec3>()
+            / total_mass;
+        let momentum = sim
+            .velocities()
+            .iter()
+            .zip(masses)
+            .map(|(v, m)| *v * *m)
+            .sum();
+        (total_mass, center, momentum)
+    }
+
+    #[test]
+    fn disc_galaxy_is_centred_and_balanced() {
+        let galaxy = galaxy();
+        let config = SimulationConfig::default();
+        let mut sim = Simulation::new(config);
+        galaxy.generate(&mut sim);
+
+        let halo = galaxy.halo.unwrap();
+        assert_eq!(
+            sim.len(),
+            galaxy.disc_count + galaxy.bulge.count + halo.count
+        );
+        let (total_mass, center, momentum) = totals(&sim);
+        let expected_mass = galaxy.disc_mass + galaxy.bulge.mass + halo.mass;
+        assert!((total_mass - expected_mass).abs() < 1e-9 * expected_mass);
+        assert!(center.length() < 1e-9, "centre of mass {:?}", center);
+        assert!(momentum.length() < 1e-9, "momentum {:?}", momentum);
+
+        // Disc bodies' centripetal accelerations should match the pull of the whole galaxy, found
+        // here by direct summation rather than the generator's tree.
+        let mut accelerations = vec![DVec3::ZERO; sim.len()];
+        direct::accelerations(
+            sim.positions(),
+            sim.masses(),
+            config.units.gravitational_constant(),
+            config.softening,
+            &mut accelerations,
+        );
+        let mut errors: Vec<f64> = sim.positions()[..galaxy.disc_count]
+            .iter()
+            .zip(sim.velocities())
+            .zip(&accelerations)
+            .filter_map(|((position, velocity), acceleration)| {
+                let radial = DVec3::new(position.x, position.y, 0.0);
+                let inward = -acceleration.dot(radial.normalize());
+                let centripetal = velocity.length_squared() / radial.length();
+                // The odd body pulled outwards by a close neighbour is left at rest.
+                (inward > 0.0).then(|| (centripetal - inward).abs() / inward)
+            })
+            .collect();
+        assert!(errors.len() > galaxy.disc_count * 98 / 100);
+        errors.sort_by(f64::total_cmp);
+        // What remains is the generator's Barnes-Hut approximation.
+        let median = errors[errors.len() / 2];
+        let tenth_worst = errors[errors.len() * 9 / 10];
+        assert!(median < 0.005, "median error {}", median);
+        assert!(tenth_worst < 0.01, "90th percentile error {}", tenth_worst);
+    }
+
+    #[test]
+    fn galaxy_collision_is_centred_and_approaching() {
+        let galaxy = galaxy();
+        let collision = GalaxyCollision {
+            separation: 30.0,
+            impact_parameter: 6.0,
+            relative_speed: 12.0,
+            inclination: 1.0,
+        };
+        let mut sim = Simulation::new(SimulationConfig::default());
+        collision.generate(&galaxy, &mut sim);
+
+        let count = galaxy.disc_count + galaxy.bulge.count + galaxy.halo.unwrap().count;
+        assert_eq!(sim.len(), 2 * count);
+        let (total_mass, center, momentum) = totals(&sim);
+        let galaxy_mass = galaxy.disc_mass + galaxy.bulge.mass + galaxy.halo.unwrap().mass;
+        assert!((total_mass - 2.0 * galaxy_mass).abs() < 1e-9 * galaxy_mass);
+        assert!(center.length() < 1e-9, "centre of mass {:?}", center);
+        assert!(momentum.length() < 1e-9, "momentum {:?}", momentum);
+
+        // Each galaxy's own centre of mass starts half the separation from the origin, moving
+        // towards the other at half the relative speed.
+        let first = |values: &[DVec3]| {
+            values[..count]
+                .iter()
+                .zip(sim.masses())
+                .map(|(value, m)| *value * *m)
+                .sum::<DVec3>()
+                / galaxy_mass
+        };
+        let offset = first(sim.positions());
+        let velocity = first(sim.velocities());
+        assert!(
+            (offset - DVec3::new(-15.0, -3.0, 0.0)).length() < 1e-9,
+            "{:?}",
+            offset
+        );
+        assert!(
+            (velocity - DVec3::new(6.0, 0.0, 0.0)).length() < 1e-9,
+            "{:?}",
+            velocity
+        );
+    }
+}
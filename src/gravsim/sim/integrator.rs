@@ -0,0 +1,378 @@
+use glam::DVec3;
+
+use crate::gravsim::localization::Text;
+
+/// Evaluates the acceleration of every body at the given positions.
+pub type Accelerations<'a> = dyn FnMut(&[DVec3], &mut [DVec3]) + 'a;
+
+/// A scheme for advancing the bodies' positions and velocities through time.
+pub trait Integrator {
+    /// Advances `positions` and `velocities` by `dt`, calling `accelerations` for the forces.
+    fn step(
+        &mut self,
+        positions: &mut [DVec3],
+        velocities: &mut [DVec3],
+        dt: f64,
+        accelerations: &mut Accelerations,
+    );
+
+    /// Discards anything carried over from previous steps.
+    /// Called whenever bodies are added or the forces between them change.
+    fn reset(&mut self) {}
+}
+
+/// The integrators the simulation can be switched between.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IntegratorKind {
+    SemiImplicitEuler,
+    Leapfrog,
+    #[default]
+    VelocityVerlet,
+    RungeKutta4,
+    Yoshida8,
+}
+
+impl IntegratorKind {
+    pub const ALL: &[IntegratorKind] = &[
+        IntegratorKind::SemiImplicitEuler,
+        IntegratorKind::Leapfrog,
+        IntegratorKind::VelocityVerlet,
+        IntegratorKind::RungeKutta4,
+        IntegratorKind::Yoshida8,
+    ];
+
+    pub fn name(self) -> Text {
+        match self {
+            IntegratorKind::SemiImplicitEuler => Text::SemiImplicitEuler,
+            IntegratorKind::Leapfrog => Text::Leapfrog,
+            IntegratorKind::VelocityVerlet => Text::VelocityVerlet,
+            IntegratorKind::RungeKutta4 => Text::RungeKutta4,
+            IntegratorKind::Yoshida8 => Text::Yoshida8,
+        }
+    }
+
+    pub fn create(self) -> Box<dyn Integrator> {
+        match self {
+            IntegratorKind::SemiImplicitEuler => Box::new(SemiImplicitEuler::default()),
+            IntegratorKind::Leapfrog => Box::new(Leapfrog::default()),
+            IntegratorKind::VelocityVerlet => Box::new(VelocityVerlet::default()),
+            IntegratorKind::RungeKutta4 => Box::new(RungeKutta4::default()),
+            IntegratorKind::Yoshida8 => Box::new(Yoshida8::default()),
+        }
+    }
+}
+
+/// Kicks the velocities with the current accelerations, then drifts the positions.
+/// First order and cheap, but energy drifts steadily over long runs.
+#[derive(Default)]
+pub struct SemiImplicitEuler {
+    accelerations: Vec<DVec3>,
+}
+
+impl Integrator for SemiImplicitEuler {
+    fn step(
+        &mut self,
+        positions: &mut [DVec3],
+        velocities: &mut [DVec3],
+        dt: f64,
+        accelerations: &mut Accelerations,
+    ) {
+        self.accelerations.resize(positions.len(), DVec3::ZERO);
+        accelerations(positions, &mut self.accelerations);
+        for ((position, velocity), acceleration) in positions
+            .iter_mut()
+            .zip(velocities)
+            .zip(&self.accelerations)
+        {
+            *velocity += *acceleration * dt;
+            *position += *velocity * dt;
+        }
+    }
+}
+
+/// Drift-kick-drift leapfrog: drifts the positions half a step, kicks the velocities
+/// with the accelerations at the midpoint, then drifts the remaining half.
+/// Second order and symplectic, so energy errors stay bounded instead of accumulating.
+#[derive(Default)]
+pub struct Leapfrog {
+    accelerations: Vec<DVec3>,
+}
+
+impl Integrator for Leapfrog {
+    fn step(
+        &mut self,
+        positions: &mut [DVec3],
+        velocities: &mut [DVec3],
+        dt: f64,
+        accelerations: &mut Accelerations,
+    ) {
+        self.accelerations.resize(positions.len(), DVec3::ZERO);
+        drift(positions, velocities, dt * 0.5);
+        accelerations(positions, &mut self.accelerations);
+        kick(velocities, &self.accelerations, dt);
+        drift(positions, velocities, dt * 0.5);
+    }
+}
+
+/// Kick-drift-kick velocity Verlet: half kicks with the accelerations at the start and end
+/// of the step around a full drift. Second order and symplectic like [`Leapfrog`], with the
+/// positions and velocities both known at whole steps.
+///
+/// The accelerations at the end of a step are kept for the start of the next,
+/// so each step costs a single force evaluation.
+#[derive(Default)]
+pub struct VelocityVerlet {
+    accelerations: Vec<DVec3>,
+    /// Whether `accelerations` holds the accelerations at the current positions.
+    current: bool,
+}
+
+impl Integrator for VelocityVerlet {
+    fn step(
+        &mut self,
+        positions: &mut [DVec3],
+        velocities: &mut [DVec3],
+        dt: f64,
+        accelerations: &mut Accelerations,
+    ) {
+        if !self.current || self.accelerations.len() != positions.len() {
+            self.accelerations.resize(positions.len(), DVec3::ZERO);
+            accelerations(positions, &mut self.accelerations);
+        }
+        kick(velocities, &self.accelerations, dt * 0.5);
+        drift(positions, velocities, dt);
+        accelerations(positions, &mut self.accelerations);
+        kick(velocities, &self.accelerations, dt * 0.5);
+        self.current = true;
+    }
+
+    fn reset(&mut self) {
+        self.current = false;
+    }
+}
+
+/// The classic fourth-order Runge-Kutta method, costing four force evaluations per step.
+/// Very accurate over short runs, but not symplectic, so energy slowly drifts over long ones.
+#[derive(Default)]
+pub struct RungeKutta4 {
+    stage_positions: Vec<DVec3>,
+    stage_velocities: Vec<DVec3>,
+    accelerations: Vec<DVec3>,
+    position_sum: Vec<DVec3>,
+    velocity_sum: Vec<DVec3>,
+}
+
+impl Integrator for RungeKutta4 {
+    fn step(
+        &mut self,
+        positions: &mut [DVec3],
+        velocities: &mut [DVec3],
+        dt: f64,
+        accelerations: &mut Accelerations,
+    ) {
+        /// How far into the step each stage is evaluated, and its weight in the final sum.
+        const STAGES: [(f64, f64); 4] = [(0.0, 1.0), (0.5, 2.0), (0.5, 2.0), (1.0, 1.0)];
+
+        let len = positions.len();
+        self.stage_positions.resize(len, DVec3::ZERO);
+        self.stage_velocities.resize(len, DVec3::ZERO);
+        self.accelerations.resize(len, DVec3::ZERO);
+        self.position_sum.clear();
+        self.position_sum.resize(len, DVec3::ZERO);
+        self.velocity_sum.clear();
+        self.velocity_sum.resize(len, DVec3::ZERO);
+
+        for (stage, (offset, weight)) in STAGES.into_iter().enumerate() {
+            // The first stage is evaluated at the start of the step, and each later stage starts
+            // from the derivatives estimated by the previous one. Nothing is read from earlier
+            // steps, so a step is never tainted by forces left over from before a reset.
+            if stage == 0 {
+                self.stage_positions.copy_from_slice(positions);
+                self.stage_velocities.copy_from_slice(velocities);
+            } else {
+                for i in 0..len {
+                    self.stage_positions[i] =
+                        positions[i] + self.stage_velocities[i] * (offset * dt);
+                    self.stage_velocities[i] =
+                        velocities[i] + self.accelerations[i] * (offset * dt);
+                }
+            }
+            accelerations(&self.stage_positions, &mut self.accelerations);
+            for i in 0..len {
+                self.position_sum[i] += self.stage_velocities[i] * weight;
+                self.velocity_sum[i] += self.accelerations[i] * weight;
+            }
+        }
+
+        for i in 0..len {
+            positions[i] += self.position_sum[i] * (dt / 6.0);
+            velocities[i] += self.velocity_sum[i] * (dt / 6.0);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.accelerations.clear();
+    }
+}
+
+/// Yoshida's eighth-order symplectic integrator: fifteen [`Leapfrog`] steps of carefully chosen
+/// lengths, some of them negative, whose errors cancel up to eighth order.
+/// Costs fifteen force evaluations per step, which pays off for high-accuracy few-body runs.
+#[derive(Default)]
+pub struct Yoshida8 {
+    leapfrog: Leapfrog,
+}
+
+impl Yoshida8 {
+    /// Solution D from Yoshida (1990), "Construction of higher order symplectic integrators".
+    /// The remaining weight, at the centre of the symmetric sequence, makes the weights sum to one.
+    const WEIGHTS: [f64; 7] = [
+        0.914844246229740,
+        0.253693336566229,
+        -1.44485223686048,
+        -0.158240635368243,
+        1.93813913762276,
+        -1.96061023297549,
+        0.102799849391985,
+    ];
+}
+
+impl Integrator for Yoshida8 {
+    fn step(
+        &mut self,
+        positions: &mut [DVec3],
+        velocities: &mut [DVec3],
+        dt: f64,
+        accelerations: &mut Accelerations,
+    ) {
+        let centre = 1.0 - 2.0 * Self::WEIGHTS.iter().sum::<f64>();
+        let weights = Self::WEIGHTS
+            .iter()
+            .copied()
+            .chain(std::iter::once(centre))
+            .chain(Self::WEIGHTS.iter().rev().copied());
+        for weight in weights {
+            self.leapfrog
+                .step(positions, velocities, weight * dt, accelerations);
+        }
+    }
+}
+
+fn drift(positions: &mut [DVec3], velocities: &[DVec3], dt: f64) {
+    for (position, velocity) in positions.iter_mut().zip(velocities) {
+        *position += *velocity * dt;
+    }
+}
+
+fn kick(velocities: &mut [DVec3], accelerations: &[DVec3], dt: f64) {
+    for (velocity, acceleration) in velocities.iter_mut().zip(accelerations) {
+        *velocity += *acceleration * dt;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::DVec3;
+
+    use super::IntegratorKind;
+
+    /// A unit-frequency harmonic oscillator, whose exact solution from x = 1, v = 0 is cos(t).
+    fn spring(positions: &[DVec3], accelerations: &mut [DVec3]) {
+        for (acceleration, position) in accelerations.iter_mut().zip(positions) {
+            *acceleration = -*position;
+        }
+    }
+
+    #[test]
+    fn integrators_follow_a_harmonic_oscillator() {
+        for kind in IntegratorKind::ALL {
+            let mut integrator = kind.create();
+            let mut positions = [DVec3::X];
+            let mut velocities = [DVec3::ZERO];
+            let steps = 1000;
+            for _ in 0..steps {
+                integrator.step(
+                    &mut positions,
+                    &mut velocities,
+                    1.0 / steps as f64,
+                    &mut spring,
+                );
+            }
+            let error = (positions[0].x - 1.0f64.cos()).abs();
+            assert!(error < 1e-3, "{:?}: error {}", kind, error);
+        }
+    }
+
+    /// The phase-space error after following the oscillator from t = 0 to 2 in `steps` steps.
+    fn oscillator_error(kind: IntegratorKind, steps: usize) -> f64 {
+        let mut integrator = kind.create();
+        let mut positions = [DVec3::X];
+        let mut velocities = [DVec3::ZERO];
+        let duration = 2.0;
+        for _ in 0..steps {
+            integrator.step(
+                &mut positions,
+                &mut velocities,
+                duration / steps as f64,
+                &mut spring,
+            );
+        }
+        let (sin, cos) = f64::sin_cos(duration);
+        (positions[0] - DVec3::X * cos)
+            .length()
+            .hypot((velocities[0] + DVec3::X * sin).length())
+    }
+
+    /// Halving the step of an integrator of order p should cut its error by about 2^p. A wrong
+    /// stage or coefficient usually leaves the method consistent but of lower order, which a
+    /// plain accuracy bound can't tell apart from a correct lower-order method.
+    #[test]
+    fn integrators_converge_at_their_order() {
+        // Step counts keep the error well above rounding at the finer step.
+        for (kind, order, steps) in [
+            (IntegratorKind::SemiImplicitEuler, 1, 64),
+            (IntegratorKind::Leapfrog, 2, 64),
+            (IntegratorKind::VelocityVerlet, 2, 64),
+            (IntegratorKind::RungeKutta4, 4, 16),
+            (IntegratorKind::Yoshida8, 8, 16),
+        ] {
+            let ratio = oscillator_error(kind, steps) / oscillator_error(kind, 2 * steps);
+            let expected = 2.0f64.powi(order);
+            assert!(
+                ratio > 0.8 * expected && ratio < 1.25 * expected,
+                "{:?}: halving the step reduced the error by {}, expected about {}",
+                kind,
+                ratio,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn integrators_recover_from_non_finite_forces_after_reset() {
+        for kind in IntegratorKind::ALL {
+            let mut integrator = kind.create();
+            let mut positions = [DVec3::X];
+            let mut velocities = [DVec3::ZERO];
+            integrator.step(
+                &mut positions,
+                &mut velocities,
+                0.01,
+                &mut |_: &[DVec3], accelerations: &mut [DVec3]| accelerations.fill(DVec3::NAN),
+            );
+
+            // As after the watchdog restores a snapshot.
+            let mut positions = [DVec3::X];
+            let mut velocities = [DVec3::ZERO];
+            integrator.reset();
+            integrator.step(&mut positions, &mut velocities, 0.01, &mut spring);
+            assert!(
+                positions[0].is_finite() && velocities[0].is_finite(),
+                "{:?}: position {:?}, velocity {:?}",
+                kind,
+                positions[0],
+                velocities[0]
+            );
+        }
+    }
+}
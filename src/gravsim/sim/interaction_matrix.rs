@@ -0,0 +1,201 @@
+use crate::gravsim::{
+    localization::{Locale, Text},
+    sim::{Simulation, direct},
+};
+
+/// Systems with more bodies than this are too large to show one cell per pair.
+const MAX_BODIES: usize = 64;
+
+/// The largest width a cell is drawn at, so that few-body matrices stay compact.
+const MAX_CELL_SIZE: f32 = 24.0;
+
+/// The pairwise quantity shown in the matrix.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Quantity {
+    /// The magnitude of the gravitational force between the bodies.
+    #[default]
+    Force,
+    /// The magnitude of the bodies' mutual potential energy.
+    Energy,
+}
+
+impl Quantity {
+    pub const ALL: &[Quantity] = &[Quantity::Force, Quantity::Energy];
+
+    pub fn name(self) -> Text {
+        match self {
+            Quantity::Force => Text::Force,
+            Quantity::Energy => Text::PotentialEnergy,
+        }
+    }
+}
+
+/// A window showing the force or potential energy between every pair of bodies as a heatmap,
+/// for watching how the mutual interactions of a few-body system evolve.
+pub struct InteractionMatrixWindow {
+    quantity: Quantity,
+    /// Colours cells by the logarithm of their value, since interactions span many orders of
+    /// magnitude as bodies approach each other.
+    log_scale: bool,
+    values: Vec<f64>,
+}
+
+impl Default for InteractionMatrixWindow {
+    fn default() -> Self {
+        Self {
+            quantity: Quantity::default(),
+            log_scale: true,
+            values: Vec::new(),
+        }
+    }
+}
+
+impl InteractionMatrixWindow {
+    pub fn build(&mut self, ui: &imgui::Ui, locale: Locale, sim: &Simulation, opened: &mut bool) {
+        let title = format!(
+            "{}###InteractionMatrix",
+            locale.text(Text::InteractionMatrixTitle)
+        );
+        ui.window(title)
+            .opened(opened)
+            .always_auto_resize(true)
+            .build(|| {
+                let count = sim.len();
+                if count > MAX_BODIES {
+                    ui.text_disabled(format!(
+                        "{} {}",
+                        locale.text(Text::InteractionMatrixLimit),
+                        MAX_BODIES
+                    ));
+                    ui.text_disabled(format!("{}: {}", locale.text(Text::Bodies), count));
+                    return;
+                }
+
+                let mut index = Quantity::ALL
+                    .iter()
+                    .position(|quantity| *quantity == self.quantity)
+                    .unwrap_or_default();
+                if ui.combo(
+                    locale.text(Text::Quantity),
+                    &mut index,
+                    Quantity::ALL,
+                    |quantity| locale.text(quantity.name()).into(),
+                ) {
+                    self.quantity = Quantity::ALL[index];
+                }
+                ui.checkbox(locale.text(Text::LogScale), &mut self.log_scale);
+
+                self.update(sim);
+                let (min, max) = self
+                    .values
+                    .iter()
+                    .copied()
+                    .filter(|value| *value > 0.0)
+                    .fold((f64::INFINITY, 0.0), |(min, max), value| {
+                        (f64::min(min, value), f64::max(max, value))
+                    });
+                if max == 0.0 {
+                    ui.text_disabled(locale.text(Text::NoInteractingPairs));
+                    return;
+                }
+                ui.text(format!(
+                    "{} {:.3e} {} {:.3e}",
+                    locale.text(Text::Range),
+                    min,
+                    locale.text(Text::To),
+                    max
+                ));
+                self.heatmap(ui, locale, count, min, max);
+            });
+    }
+
+    /// Recomputes every pair's value, stored row by row with zeros on the diagonal.
+    fn update(&mut self, sim: &Simulation) {
+        let count = sim.len();
+        let config = sim.config();
+        self.values.clear();
+        self.values.resize(count * count, 0.0);
+        for i in 0..count {
+            for j in (i + 1)..count {
+                let (force, energy) = direct::pair_interaction(
+                    sim.positions(),
+                    sim.masses(),
+                    config.units.gravitational_constant(),
+                    config.softening,
+                    i,
+                    j,
+                );
+                let value = match self.quantity {
+                    Quantity::Force => force,
+                    Quantity::Energy => energy.abs(),
+                };
+                self.values[i * count + j] = value;
+                self.values[j * count + i] = value;
+            }
+        }
+    }
+
+    /// Draws one cell per pair, coloured from dark blue for the weakest interaction to yellow
+    /// for the strongest, with the exact value shown on hover.
+    fn heatmap(&self, ui: &imgui::Ui, locale: Locale, count: usize, min: f64, max: f64) {
+        let cell_size = (ui.content_region_avail()[0].max(256.0) / count as f32)
+            .floor()
+            .clamp(2.0, MAX_CELL_SIZE);
+        let origin = ui.cursor_screen_pos();
+        let draw_list = ui.get_window_draw_list();
+        let mouse = ui.io().mouse_pos;
+
+        for i in 0..count {
+            for j in 0..count {
+                let x0 = origin[0] + j as f32 * cell_size;
+                let y0 = origin[1] + i as f32 * cell_size;
+                let x1 = x0 + cell_size - 1.0;
+                let y1 = y0 + cell_size - 1.0;
+
+                let value = self.values[i * count + j];
+                let color = if i == j {
+                    [0.15, 0.15, 0.15, 1.0]
+                } else {
+                    let fraction = if self.log_scale && min < max {
+                        (value.max(min) / min).ln() / (max / min).ln()
+                    } else {
+                        value / max
+                    };
+                    heat_color(fraction as f32)
+                };
+                draw_list
+                    .add_rect([x0, y0], [x1, y1], color)
+                    .filled(true)
+                    .build();
+
+                if i != j && (x0..x1).contains(&mouse[0]) && (y0..y1).contains(&mouse[1]) {
+                    ui.tooltip_text(format!(
+                        "{} {} {} {}: {:.4e}",
+                        locale.text(Text::Bodies),
+                        i,
+                        locale.text(Text::And),
+                        j,
+                        value
+                    ));
+                }
+            }
+        }
+
+        ui.dummy([count as f32 * cell_size, count as f32 * cell_size]);
+    }
+}
+
+/// Maps `fraction` in `0..=1` onto a dark blue, red and yellow colour ramp.
+fn heat_color(fraction: f32) -> [f32; 4] {
+    const STOPS: [[f32; 3]; 3] = [[0.05, 0.05, 0.35], [0.85, 0.2, 0.15], [1.0, 0.95, 0.4]];
+    let position = fraction.clamp(0.0, 1.0) * (STOPS.len() - 1) as f32;
+    let index = (position as usize).min(STOPS.len() - 2);
+    let t = position - index as f32;
+    let [from, to] = [STOPS[index], STOPS[index + 1]];
+    [
+        from[0] + (to[0] - from[0]) * t,
+        from[1] + (to[1] - from[1]) * t,
+        from[2] + (to[2] - from[2]) * t,
+        1.0,
+    ]
+}
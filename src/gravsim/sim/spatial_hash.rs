@@ -0,0 +1,145 @@
+use glam::{DVec3, I64Vec3};
+
+/// A uniform grid over space whose cells are hashed into a fixed-size table,
+/// for finding the bodies near a point without visiting every body.
+///
+/// Only occupied cells take up room, so bodies can be spread over any region of space.
+/// Cells that hash to the same bucket share it, which costs some extra distance checks
+/// but never misses a neighbour.
+#[derive(Default)]
+pub struct SpatialHash {
+    cell_size: f64,
+    /// Each bucket's bodies are `entries[bucket_starts[bucket]..bucket_starts[bucket + 1]]`.
+    bucket_starts: Vec<u32>,
+    entries: Vec<u32>,
+}
+
+impl SpatialHash {
+    /// Rebuilds the grid for `positions` with cubic cells of width `cell_size`,
+    /// which must be at least the largest radius that will be searched.
+    pub fn build(&mut self, positions: &[DVec3], cell_size: f64) {
+        self.cell_size = cell_size;
+        let buckets = (positions.len() * 2).next_power_of_two();
+        self.bucket_starts.clear();
+        self.bucket_starts.resize(buckets + 1, 0);
+        self.entries.clear();
+        self.entries.resize(positions.len(), 0);
+
+        // Counting sort: count each bucket's bodies, turn the counts into end offsets,
+        // then place each body by counting its bucket's offset back down.
+        for position in positions {
+            let bucket = self.bucket(self.cell(*position));
+            self.bucket_starts[bucket] += 1;
+        }
+        let mut total = 0;
+        for start in &mut self.bucket_starts {
+            total += *start;
+            *start = total;
+        }
+        for (body, position) in positions.iter().enumerate() {
+            let bucket = self.bucket(self.cell(*position));
+            self.bucket_starts[bucket] -= 1;
+            self.entries[self.bucket_starts[bucket] as usize] = body as u32;
+        }
+    }
+
+    /// Calls `f` with every body within `radius` of `point`, using the positions the grid was built from.
+    /// `radius` must not exceed the cell size, so that at most 3×3×3 cells are searched.
+    pub fn for_each_neighbor(
+        &self,
+        positions: &[DVec3],
+        point: DVec3,
+        radius: f64,
+        mut f: impl FnMut(usize),
+    ) {
+        if self.entries.is_empty() {
+            return;
+        }
+        debug_assert!(radius <= self.cell_size);
+        let radius_squared = radius * radius;
+        let min = self.cell(point - DVec3::splat(radius));
+        let max = self.cell(point + DVec3::splat(radius));
+
+        // Neighbouring cells can share a bucket; each bucket is only searched once.
+        // Rounding at cell boundaries can add a fourth cell along an axis.
+        let mut searched = [usize::MAX; 64];
+        let mut searched_count = 0;
+        for z in min.z..=max.z {
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    let bucket = self.bucket(I64Vec3::new(x, y, z));
+                    if searched[..searched_count].contains(&bucket) {
+                        continue;
+                    }
+                    searched[searched_count] = bucket;
+                    searched_count += 1;
+
+                    let start = self.bucket_starts[bucket] as usize;
+                    let end = self.bucket_starts[bucket + 1] as usize;
+                    for &body in &self.entries[start..end] {
+                        if positions[body as usize].distance_squared(point) <= radius_squared {
+                            f(body as usize);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn cell(&self, position: DVec3) -> I64Vec3 {
+        (position / self.cell_size).floor().as_i64vec3()
+    }
+
+    fn bucket(&self, cell: I64Vec3) -> usize {
+        let hash = (cell.x.wrapping_mul(73_856_093))
+            ^ (cell.y.wrapping_mul(19_349_663))
+            ^ (cell.z.wrapping_mul(83_492_791));
+        hash as usize & (self.bucket_starts.len() - 2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::DVec3;
+
+    use super::SpatialHash;
+
+    #[test]
+    fn neighbors_match_brute_force() {
+        let cell_size = 0.5;
+        // A lattice straddling the origin at half the cell size, so that every other point lies on
+        // a cell boundary, and points a hair either side of boundaries.
+        let mut positions = Vec::new();
+        for z in -4..4 {
+            for y in -4..4 {
+                for x in -4..4 {
+                    let point = DVec3::new(x as f64, y as f64, z as f64) * (cell_size * 0.5);
+                    positions.push(point);
+                    positions.push(point + DVec3::splat(1e-12));
+                    positions.push(point - DVec3::new(1e-12, 0.0, 1e-12));
+                }
+            }
+        }
+        let mut hash = SpatialHash::default();
+        hash.build(&positions, cell_size);
+
+        for radius in [cell_size, cell_size * 0.5, cell_size * 0.3] {
+            for (index, &point) in positions.iter().enumerate().step_by(5) {
+                // Query from bodies themselves and from points between them.
+                for point in [point, point + DVec3::new(0.1, -0.07, 0.03)] {
+                    let mut found = Vec::new();
+                    hash.for_each_neighbor(&positions, point, radius, |body| found.push(body));
+                    found.sort_unstable();
+                    let expected: Vec<usize> = (0..positions.len())
+                        .filter(|&body| positions[body].distance_squared(point) <= radius * radius)
+                        .collect();
+                    assert_eq!(
+                        found, expected,
+                        "radius {} around {:?} (near body {})",
+                        radius, point, index
+                    );
+                }
+            }
+        }
+    }
+}
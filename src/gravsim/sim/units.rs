@@ -0,0 +1,71 @@
+use crate::gravsim::localization::Text;
+
+/// The units that positions, velocities, masses and time are measured in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UnitSystem {
+    /// Dimensionless units with G = 1.
+    #[default]
+    NBody,
+    /// Astronomical units, solar masses and days.
+    SolarSystem,
+    /// Kiloparsecs, solar masses and megayears.
+    Galactic,
+}
+
+impl UnitSystem {
+    pub const ALL: &[UnitSystem] = &[
+        UnitSystem::NBody,
+        UnitSystem::SolarSystem,
+        UnitSystem::Galactic,
+    ];
+
+    pub fn name(self) -> Text {
+        match self {
+            UnitSystem::NBody => Text::UnitsNBody,
+            UnitSystem::SolarSystem => Text::UnitsSolarSystem,
+            UnitSystem::Galactic => Text::UnitsGalactic,
+        }
+    }
+
+    /// The gravitational constant expressed in these units.
+    pub fn gravitational_constant(self) -> f64 {
+        match self {
+            UnitSystem::NBody => 1.0,
+            // The square of the Gaussian gravitational constant k = 0.01720209895.
+            UnitSystem::SolarSystem => 2.959_122_082_855_911e-4,
+            UnitSystem::Galactic => 4.498_502_151_469_554e-12,
+        }
+    }
+
+    /// Formats a duration given in these units' time unit for display,
+    /// choosing the calendar units that suit its magnitude.
+    pub fn format_time(self, time: f64) -> String {
+        match self {
+            UnitSystem::NBody => format!("{:.3}", time),
+            UnitSystem::SolarSystem => {
+                const DAYS_PER_YEAR: f64 = 365.25;
+                let magnitude = time.abs();
+                if magnitude < 1.0 {
+                    format!("{:.2} h", time * 24.0)
+                } else if magnitude < DAYS_PER_YEAR {
+                    format!("{:.2} d", time)
+                } else {
+                    let years = (magnitude / DAYS_PER_YEAR).floor();
+                    let days = magnitude - years * DAYS_PER_YEAR;
+                    let sign = if time < 0.0 { "-" } else { "" };
+                    format!("{}{} y {:.1} d", sign, years, days)
+                }
+            }
+            UnitSystem::Galactic => {
+                let magnitude = time.abs();
+                if magnitude < 1.0 {
+                    format!("{:.1} kyr", time * 1000.0)
+                } else if magnitude < 1000.0 {
+                    format!("{:.2} Myr", time)
+                } else {
+                    format!("{:.3} Gyr", time / 1000.0)
+                }
+            }
+        }
+    }
+}
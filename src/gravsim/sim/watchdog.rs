@@ -0,0 +1,165 @@
+use std::time::{Duration, Instant};
+
+use crate::gravsim::{
+    localization::{Locale, Text},
+    sim::{Simulation, Snapshot},
+};
+
+/// How often, in wall-clock time, a healthy simulation is snapshotted for recovery.
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How many offending bodies are listed in the diagnostic dialog.
+const LISTED_BODIES: usize = 10;
+
+/// A numerical blow-up found by the watchdog.
+struct BlowUp {
+    /// The first few bodies with a non-finite position or velocity.
+    bodies: Vec<usize>,
+    count: usize,
+    time: f64,
+}
+
+/// Watches a simulation for positions or velocities that have become NaN or infinite,
+/// which would otherwise make bodies silently vanish from the scene.
+///
+/// Healthy states are snapshotted periodically, so that the simulation can be rolled back to
+/// the last known good state once a blow-up is found.
+#[derive(Default)]
+pub struct Watchdog {
+    last_good: Option<Snapshot>,
+    /// When `last_good` was taken, or `None` to take one on the next healthy check.
+    last_snapshot: Option<Instant>,
+    blow_up: Option<BlowUp>,
+}
+
+impl Watchdog {
+    /// Whether a blow-up has been found and not yet dismissed.
+    pub fn tripped(&self) -> bool {
+        self.blow_up.is_some()
+    }
+
+    /// Checks every body of `sim`, returning true if a blow-up was found.
+    /// Called after the simulation is advanced; the caller should pause it when this trips.
+    pub fn check(&mut self, sim: &Simulation) -> bool {
+        let mut offending = sim
+            .positions()
+            .iter()
+            .zip(sim.velocities())
+            .enumerate()
+            .filter(|(_, (position, velocity))| !position.is_finite() || !velocity.is_finite())
+            .map(|(index, _)| index);
+
+        let bodies: Vec<usize> = offending.by_ref().take(LISTED_BODIES).collect();
+        if bodies.is_empty() {
+            if self
+                .last_snapshot
+                .is_none_or(|taken| taken.elapsed() >= SNAPSHOT_INTERVAL)
+            {
+                self.last_good = Some(sim.snapshot());
+                self.last_snapshot = Some(Instant::now());
+            }
+            return false;
+        }
+
+        let count = bodies.len() + offending.count();
+        log::error!(
+            "Numerical blow-up at t = {}: {} bodies have non-finite positions or velocities, first {:?}",
+            sim.time(),
+            count,
+            bodies
+        );
+        self.blow_up = Some(BlowUp {
+            bodies,
+            count,
+            time: sim.time(),
+        });
+        true
+    }
+
+    /// Draws the diagnostic dialog while a blow-up is pending, offering to restore
+    /// the last known good state. Drawn from the HUD, so it also shows in presentation mode.
+    pub fn dialog(&mut self, ui: &imgui::Ui, locale: Locale, sim: &mut Simulation) {
+        let Some(blow_up) = &self.blow_up else {
+            return;
+        };
+        let units = sim.config().units;
+
+        let mut dismissed = false;
+        ui.window(format!("{}###Watchdog", locale.text(Text::WatchdogTitle)))
+            .always_auto_resize(true)
+            .collapsible(false)
+            .build(|| {
+                ui.text(format!(
+                    "{} {}",
+                    locale.text(Text::WatchdogPausedAt),
+                    units.format_time(blow_up.time)
+                ));
+                ui.text(format!(
+                    "{} {}",
+                    locale.text(Text::WatchdogBodyCount),
+                    blow_up.count
+                ));
+                ui.text(locale.text(Text::WatchdogHint));
+
+                ui.separator();
+                for &body in &blow_up.bodies {
+                    ui.text(format!(
+                        "{} {}: {} {:?}, {} {:?}",
+                        locale.text(Text::Body),
+                        body,
+                        locale.text(Text::Position),
+                        sim.positions()[body],
+                        locale.text(Text::Velocity),
+                        sim.velocities()[body]
+                    ));
+                }
+                if blow_up.count > blow_up.bodies.len() {
+                    ui.text_disabled(format!(
+                        "... {} {}",
+                        blow_up.count - blow_up.bodies.len(),
+                        locale.text(Text::More)
+                    ));
+                }
+
+                ui.separator();
+                if let Some(snapshot) = &self.last_good {
+                    ui.text(format!(
+                        "{} {}",
+                        locale.text(Text::WatchdogLastGood),
+                        units.format_time(snapshot.time())
+                    ));
+                    if ui.button(locale.text(Text::Restore)) {
+                        sim.restore(snapshot);
+                        dismissed = true;
+                    }
+                    ui.same_line();
+                } else {
+                    ui.text_disabled(locale.text(Text::WatchdogNoLastGood));
+                }
+                if ui.button(locale.text(Text::Dismiss)) {
+                    dismissed = true;
+                }
+            });
+
+        if dismissed {
+            self.blow_up = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::DVec3;
+
+    use super::Watchdog;
+    use crate::gravsim::sim::{Simulation, SimulationConfig};
+
+    #[test]
+    fn first_healthy_check_takes_a_snapshot() {
+        let mut sim = Simulation::new(SimulationConfig::default());
+        sim.add_body(DVec3::ZERO, DVec3::ZERO, 1.0, 0.0);
+        let mut watchdog = Watchdog::default();
+        assert!(!watchdog.check(&sim));
+        assert!(watchdog.last_good.is_some());
+    }
+}
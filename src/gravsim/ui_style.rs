@@ -0,0 +1,148 @@
+use crate::gravsim::settings::{FontSettings, GlyphRanges, Settings};
+
+/// The size of the UI font, in logical pixels, before any UI scaling.
+const BASE_FONT_SIZE: f32 = 13.0;
+
+/// Icon fonts such as Font Awesome place their glyphs in the Unicode private use area.
+const ICON_GLYPH_RANGES: &[u32] = &[0xe000, 0xf8ff, 0];
+
+/// The colour scheme used for the UI.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+    Classic,
+}
+
+impl Theme {
+    pub const ALL: &[Theme] = &[Theme::Dark, Theme::Light, Theme::Classic];
+}
+
+/// Applies fonts and style to an imgui context for a given display scale and theme.
+///
+/// `hidpi_factor` is the window's scale factor, and `ui_scale` the user's manual multiplier.
+/// The font atlas is rasterised at the full physical size so that text stays sharp,
+/// while the global font scale maps it back into imgui's logical coordinates.
+pub struct UiStyle {
+    base_style: imgui::Style,
+    applied_scale: Option<(f64, f32)>,
+    applied_theme: Option<Theme>,
+    ui_font: Option<Vec<u8>>,
+    glyph_ranges: GlyphRanges,
+    icon_font: Option<Vec<u8>>,
+}
+
+impl UiStyle {
+    /// Creates the style for `context`, loading any custom fonts named in `fonts`.
+    /// Fonts that fail to load are logged and fall back to imgui's built-in font.
+    pub fn new(context: &imgui::Context, fonts: &FontSettings) -> Self {
+        Self {
+            base_style: *context.style(),
+            applied_scale: None,
+            applied_theme: None,
+            ui_font: fonts.ui_font.as_deref().and_then(Self::load_font),
+            glyph_ranges: fonts.glyph_ranges,
+            icon_font: fonts.icon_font.as_deref().and_then(Self::load_font),
+        }
+    }
+
+    fn load_font(path: &std::path::Path) -> Option<Vec<u8>> {
+        match std::fs::read(path) {
+            Ok(data) => {
+                log::info!("Loaded font {:?}", path);
+                Some(data)
+            }
+            Err(e) => {
+                log::error!("Failed to load font {:?}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    /// Reapplies the context's fonts and style if the scale or theme has changed since the last call.
+    /// Returns true when the font atlas was rebuilt and the renderer's font texture must be reloaded.
+    pub fn update(
+        &mut self,
+        context: &mut imgui::Context,
+        hidpi_factor: f64,
+        settings: &Settings,
+    ) -> bool {
+        let ui_scale = settings.ui_scale;
+        let fonts_changed = self.applied_scale != Some((hidpi_factor, ui_scale));
+        if !fonts_changed && self.applied_theme == Some(settings.theme) {
+            return false;
+        }
+        self.applied_scale = Some((hidpi_factor, ui_scale));
+        self.applied_theme = Some(settings.theme);
+
+        let style = context.style_mut();
+        *style = self.base_style;
+        match settings.theme {
+            Theme::Dark => style.use_dark_colors(),
+            Theme::Light => style.use_light_colors(),
+            Theme::Classic => style.use_classic_colors(),
+        };
+        style.scale_all_sizes(ui_scale);
+
+        if !fonts_changed {
+            return false;
+        }
+
+        log::info!(
+            "Applying UI scale {:.2} (hidpi factor {:.2})",
+            ui_scale,
+            hidpi_factor
+        );
+
+        let font_size = BASE_FONT_SIZE * hidpi_factor as f32 * ui_scale;
+        let ui_font_config = imgui::FontConfig {
+            size_pixels: font_size,
+            oversample_h: 1,
+            pixel_snap_h: true,
+            glyph_ranges: match self.glyph_ranges {
+                GlyphRanges::Latin => imgui::FontGlyphRanges::default(),
+                GlyphRanges::Cyrillic => imgui::FontGlyphRanges::cyrillic(),
+                GlyphRanges::Japanese => imgui::FontGlyphRanges::japanese(),
+                GlyphRanges::Korean => imgui::FontGlyphRanges::korean(),
+                GlyphRanges::ChineseFull => imgui::FontGlyphRanges::chinese_full(),
+                GlyphRanges::Thai => imgui::FontGlyphRanges::thai(),
+                GlyphRanges::Vietnamese => imgui::FontGlyphRanges::vietnamese(),
+            },
+            ..Default::default()
+        };
+
+        // Every source in a single `add_font` call is merged into one font,
+        // so icons can be used inline with regular text.
+        let mut sources = vec![match &self.ui_font {
+            Some(data) => imgui::FontSource::TtfData {
+                data,
+                size_pixels: font_size,
+                config: Some(ui_font_config),
+            },
+            None => imgui::FontSource::DefaultFontData {
+                config: Some(ui_font_config),
+            },
+        }];
+        if let Some(data) = &self.icon_font {
+            sources.push(imgui::FontSource::TtfData {
+                data,
+                size_pixels: font_size,
+                config: Some(imgui::FontConfig {
+                    size_pixels: font_size,
+                    pixel_snap_h: true,
+                    glyph_min_advance_x: font_size,
+                    glyph_ranges: imgui::FontGlyphRanges::from_slice(ICON_GLYPH_RANGES),
+                    ..Default::default()
+                }),
+            });
+        }
+
+        let fonts = context.fonts();
+        fonts.clear();
+        fonts.add_font(&sources);
+        context.io_mut().font_global_scale = 1.0 / hidpi_factor as f32;
+
+        true
+    }
+}
@@ -1,13 +1,45 @@
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+    path::PathBuf,
+    sync::Arc,
+};
 
 use wgpu::util::DeviceExt;
 use winit::{dpi::Size, event::WindowEvent, event_loop::ActiveEventLoop, window};
 
 use crate::gravsim::{
     application::Application,
-    shader::{FragmentShader, VertexShader},
+    crash,
+    frame_pacer::FramePacer,
+    keybindings::{self, Action},
+    logging::{self, LoggingWindow},
+    profiler::{self, ProfilerWindow},
+    settings::{self, GpuDebugSettings, Settings},
+    shader::{FragmentShader, ShaderDefines, VertexShader},
+    ui_style::UiStyle,
 };
 
+/// Identifies a compiled shader module by its source and the defines it was built with.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct ShaderKey {
+    label: String,
+    source_hash: u64,
+    defines: ShaderDefines,
+}
+
+/// Identifies a render pipeline by the shader variants it was built from and its fixed state.
+#[derive(PartialEq, Eq, Hash)]
+struct PipelineKey {
+    vertex: ShaderKey,
+    vertex_entry_point: Option<String>,
+    /// Stride, step mode and attributes of each vertex buffer.
+    vertex_buffers: Vec<(u64, wgpu::VertexStepMode, Vec<wgpu::VertexAttribute>)>,
+    fragment: ShaderKey,
+    fragment_entry_point: Option<String>,
+    topology: wgpu::PrimitiveTopology,
+}
+
 pub struct WindowSurface<App: Application> {
     surface: wgpu::Surface<'static>,
     device: wgpu::Device,
@@ -17,13 +49,30 @@ pub struct WindowSurface<App: Application> {
     imgui_context: imgui::Context,
     imgui_platform: imgui_winit_support::WinitPlatform,
     imgui_renderer: imgui_wgpu::Renderer,
+    ui_style: UiStyle,
     last_frame_time: std::time::Instant,
+    frame_pacer: FramePacer,
+    shader_cache: HashMap<ShaderKey, wgpu::ShaderModule>,
+    pipeline_cache: HashMap<PipelineKey, wgpu::RenderPipeline>,
+    settings: Settings,
+    /// Hides the application's UI and every framework window, for presenting.
+    presentation_mode: bool,
+    show_help: bool,
+    show_settings: bool,
+    show_logging: bool,
+    logging_window: LoggingWindow,
+    show_profiler: bool,
+    profiler_window: ProfilerWindow,
+    crash_report: Option<PathBuf>,
     app: Option<App>,
 }
 
 pub struct RenderContext<'a> {
     encoder: &'a mut wgpu::CommandEncoder,
     view: &'a wgpu::TextureView,
+    device: &'a wgpu::Device,
+    queue: &'a wgpu::Queue,
+    size: (u32, u32),
 }
 
 pub struct RenderPassDesc {
@@ -49,6 +98,32 @@ impl<'a> RenderContext<'a> {
         });
         f(&mut render_pass);
     }
+
+    /// Creates a buffer initialised with `data`, for resources that must be replaced while running.
+    pub fn create_buffer(
+        &self,
+        label: &str,
+        data: &[u8],
+        usage: wgpu::BufferUsages,
+    ) -> wgpu::Buffer {
+        self.device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(label),
+                contents: data,
+                usage,
+            })
+    }
+
+    /// Schedules `data` to be written to the start of `buffer` before this frame's commands run.
+    /// The buffer must have been created with `wgpu::BufferUsages::COPY_DST`.
+    pub fn write_buffer(&self, buffer: &wgpu::Buffer, data: &[u8]) {
+        self.queue.write_buffer(buffer, 0, data);
+    }
+
+    /// The width of the render target divided by its height.
+    pub fn aspect_ratio(&self) -> f32 {
+        self.size.0 as f32 / self.size.1.max(1) as f32
+    }
 }
 
 impl<App: Application> WindowSurface<App> {
@@ -56,7 +131,11 @@ impl<App: Application> WindowSurface<App> {
         let start_time = std::time::Instant::now();
 
         let window = Self::create_window(event_loop);
-        let (surface, device, queue, config) = Self::create_wgpu(window.clone()).await.unwrap();
+        let settings = Settings::default();
+        let (surface, device, queue, config) =
+            Self::create_wgpu(window.clone(), &settings.gpu_debug)
+                .await
+                .unwrap();
 
         window.set_visible(true);
         window.focus_window();
@@ -68,10 +147,14 @@ impl<App: Application> WindowSurface<App> {
         let mut platform = imgui_winit_support::WinitPlatform::new(&mut context);
         platform.attach_window(
             context.io_mut(),
-            &*window,
+            &window,
             imgui_winit_support::HiDpiMode::Default,
         );
         context.set_ini_filename(None);
+
+        let mut ui_style = UiStyle::new(&context, &settings.fonts);
+        ui_style.update(&mut context, window.scale_factor(), &settings);
+
         let imgui_renderer = imgui_wgpu::Renderer::new(
             &mut context,
             &device,
@@ -91,7 +174,20 @@ impl<App: Application> WindowSurface<App> {
             imgui_context: context,
             imgui_platform: platform,
             imgui_renderer,
+            ui_style,
             last_frame_time: std::time::Instant::now(),
+            frame_pacer: FramePacer::new(),
+            shader_cache: HashMap::new(),
+            pipeline_cache: HashMap::new(),
+            settings,
+            presentation_mode: false,
+            show_help: false,
+            show_settings: false,
+            show_logging: false,
+            logging_window: LoggingWindow::default(),
+            show_profiler: false,
+            profiler_window: ProfilerWindow::default(),
+            crash_report: crash::pending_report(),
             app: None,
         };
 
@@ -119,14 +215,22 @@ impl<App: Application> WindowSurface<App> {
             return;
         }
 
+        if self.settings.limit_frame_rate {
+            let _scope = profiler::scope("Frame pacing");
+            self.frame_pacer.wait(self.settings.target_frame_rate);
+        } else {
+            self.frame_pacer.reset();
+        }
+        profiler::new_frame();
+
         let now = std::time::Instant::now();
-        self.imgui_context
-            .io_mut()
-            .update_delta_time(now - self.last_frame_time);
+        let dt = now - self.last_frame_time;
+        self.imgui_context.io_mut().update_delta_time(dt);
         self.last_frame_time = now;
 
         self.window.request_redraw();
 
+        let acquire_scope = profiler::scope("Acquire surface");
         let output = match self.surface.get_current_texture() {
             Ok(frame) => frame,
             Err(wgpu::SurfaceError::Outdated) => {
@@ -143,6 +247,7 @@ impl<App: Application> WindowSurface<App> {
             }
             Err(e) => panic!("Failed to acquire next swap chain texture: {:?}", e),
         };
+        drop(acquire_scope);
 
         let view = output
             .texture
@@ -154,20 +259,74 @@ impl<App: Application> WindowSurface<App> {
                     label: Some("Render Encoder"),
                 });
 
+        if self.ui_style.update(
+            &mut self.imgui_context,
+            self.window.scale_factor(),
+            &self.settings,
+        ) {
+            self.imgui_renderer.reload_font_texture(
+                &mut self.imgui_context,
+                &self.device,
+                &self.queue,
+            );
+        }
+
+        self.imgui_context.io_mut().config_flags.set(
+            imgui::ConfigFlags::NAV_ENABLE_KEYBOARD,
+            self.settings.keyboard_navigation,
+        );
+
         let mut app = self.app.take().expect("App must be present");
+        {
+            let _scope = profiler::scope("App update");
+            app.update(dt);
+        }
         {
             self.imgui_platform
-                .prepare_frame(self.imgui_context.io_mut(), &*self.window)
+                .prepare_frame(self.imgui_context.io_mut(), &self.window)
                 .expect("Failed to prepare frame");
             let ui = self.imgui_context.frame();
-            app.ui(ui);
+            let ui_scope = profiler::scope("UI");
+            if !self.presentation_mode {
+                app.ui(ui, self.settings.locale);
 
-            app.render(&mut RenderContext {
-                encoder: &mut encoder,
-                view: &view,
-            });
+                if self.show_help {
+                    keybindings::help_window(ui, self.settings.locale, &mut self.show_help);
+                }
+                if self.show_settings {
+                    settings::settings_window(ui, &mut self.settings, &mut self.show_settings);
+                }
+                if self.show_logging {
+                    self.logging_window
+                        .build(ui, self.settings.locale, &mut self.show_logging);
+                }
+                if let Some(report) = &self.crash_report
+                    && !crash::report_dialog(ui, self.settings.locale, report)
+                {
+                    self.crash_report = None;
+                }
+                if self.show_profiler {
+                    self.profiler_window
+                        .build(ui, self.settings.locale, &mut self.show_profiler);
+                }
+            }
+            app.hud(ui, self.settings.locale);
+            drop(ui_scope);
+
+            {
+                let _scope = profiler::scope("App render");
+                app.render(&mut RenderContext {
+                    encoder: &mut encoder,
+                    view: &view,
+                    device: &self.device,
+                    queue: &self.queue,
+                    size: (self.config.width, self.config.height),
+                });
+            }
+
+            let _scope = profiler::scope("Imgui render");
 
-            self.imgui_platform.prepare_render(ui, &*self.window);
+            self.imgui_platform.prepare_render(ui, &self.window);
 
             {
                 let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
@@ -198,8 +357,10 @@ impl<App: Application> WindowSurface<App> {
 
         self.app = Some(app);
 
+        let _scope = profiler::scope("Submit");
         self.queue.submit(std::iter::once(encoder.finish()));
         output.present();
+        logging::end_frame();
     }
 
     pub fn handle_event(
@@ -240,33 +401,17 @@ impl<App: Application> WindowSurface<App> {
                     is_synthetic
                 );
 
-                if event.logical_key == winit::keyboard::Key::Named(winit::keyboard::NamedKey::F11)
-                    && event.state == winit::event::ElementState::Pressed
-                {
-                    if self.window.fullscreen().is_some() {
-                        self.window.set_fullscreen(None);
-                        let _ = self.window.request_inner_size(Size::new(
-                            winit::dpi::LogicalSize::new(1920.0, 1080.0),
-                        ));
-                        self.resize(1920, 1080);
-                    } else {
-                        if let Some(monitor) = event_loop.primary_monitor() {
-                            let first_mode = monitor.video_modes().next();
-                            if let Some(video_mode) = first_mode {
-                                let _ = self.window.request_inner_size(Size::new(
-                                    winit::dpi::PhysicalSize {
-                                        width: video_mode.size().width,
-                                        height: video_mode.size().height,
-                                    },
-                                ));
-
-                                self.resize(video_mode.size().width, video_mode.size().height);
-
-                                self.window.set_fullscreen(Some(
-                                    winit::window::Fullscreen::Exclusive(video_mode),
-                                ));
-                            }
+                if event.state == winit::event::ElementState::Pressed && !event.repeat {
+                    match keybindings::action_for_key(&event.logical_key) {
+                        Some(Action::Fullscreen) => self.toggle_fullscreen(event_loop),
+                        Some(Action::Help) => self.show_help = !self.show_help,
+                        Some(Action::Settings) => self.show_settings = !self.show_settings,
+                        Some(Action::Logging) => self.show_logging = !self.show_logging,
+                        Some(Action::Profiler) => self.show_profiler = !self.show_profiler,
+                        Some(Action::Presentation) => {
+                            self.presentation_mode = !self.presentation_mode
                         }
+                        None => {}
                     }
                 }
             }
@@ -275,13 +420,38 @@ impl<App: Application> WindowSurface<App> {
 
         self.imgui_platform.handle_event::<()>(
             self.imgui_context.io_mut(),
-            &*self.window,
+            &self.window,
             &winit::event::Event::WindowEvent { window_id, event },
         );
 
         Ok(())
     }
 
+    fn toggle_fullscreen(&mut self, event_loop: &ActiveEventLoop) {
+        if self.window.fullscreen().is_some() {
+            self.window.set_fullscreen(None);
+            let _ = self
+                .window
+                .request_inner_size(Size::new(winit::dpi::LogicalSize::new(1920.0, 1080.0)));
+            self.resize(1920, 1080);
+        } else if let Some(monitor) = event_loop.primary_monitor() {
+            let first_mode = monitor.video_modes().next();
+            if let Some(video_mode) = first_mode {
+                let _ = self
+                    .window
+                    .request_inner_size(Size::new(winit::dpi::PhysicalSize {
+                        width: video_mode.size().width,
+                        height: video_mode.size().height,
+                    }));
+
+                self.resize(video_mode.size().width, video_mode.size().height);
+
+                self.window
+                    .set_fullscreen(Some(winit::window::Fullscreen::Exclusive(video_mode)));
+            }
+        }
+    }
+
     fn create_window(event_loop: &ActiveEventLoop) -> Arc<winit::window::Window> {
         log::info!("Creating the window");
 
@@ -321,6 +491,7 @@ impl<App: Application> WindowSurface<App> {
 
     async fn create_wgpu(
         window: Arc<winit::window::Window>,
+        gpu_debug: &GpuDebugSettings,
     ) -> anyhow::Result<(
         wgpu::Surface<'static>,
         wgpu::Device,
@@ -331,6 +502,7 @@ impl<App: Application> WindowSurface<App> {
 
         let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
             backends: wgpu::Backends::PRIMARY,
+            flags: gpu_debug.instance_flags(),
             ..Default::default()
         });
 
@@ -343,6 +515,8 @@ impl<App: Application> WindowSurface<App> {
                 force_fallback_adapter: false,
             })
             .await?;
+        crash::set_adapter_info(&adapter.get_info());
+        log::info!("Using adapter {:?}", adapter.get_info());
 
         let (device, queue) = adapter
             .request_device(&wgpu::DeviceDescriptor {
@@ -375,12 +549,38 @@ impl<App: Application> WindowSurface<App> {
         Ok((surface, device, queue, config))
     }
 
-    pub fn create_shader_module(&self, label: &str, source: &str) -> wgpu::ShaderModule {
-        self.device
+    /// Creates a shader module from WGSL source after running the preprocessor with `defines`.
+    /// Modules are cached on their source and defines, so requesting the same variant twice
+    /// returns the already compiled module.
+    pub fn create_shader_module(
+        &mut self,
+        label: &str,
+        source: &str,
+        defines: &ShaderDefines,
+    ) -> anyhow::Result<wgpu::ShaderModule> {
+        let mut hasher = DefaultHasher::new();
+        source.hash(&mut hasher);
+        let key = ShaderKey {
+            label: label.to_string(),
+            source_hash: hasher.finish(),
+            defines: defines.clone(),
+        };
+
+        if let Some(module) = self.shader_cache.get(&key) {
+            return Ok(module.clone());
+        }
+
+        let source = defines.preprocess(source)?;
+        log::info!("Compiling shader {} with defines {:?}", label, defines);
+        let module = self
+            .device
             .create_shader_module(wgpu::ShaderModuleDescriptor {
                 label: Some(label),
                 source: wgpu::ShaderSource::Wgsl(source.into()),
-            })
+            });
+        self.shader_cache.insert(key, module.clone());
+
+        Ok(module)
     }
 
     pub fn create_buffer(
@@ -397,11 +597,52 @@ impl<App: Application> WindowSurface<App> {
             })
     }
 
+    /// The key a module was cached under by [`Self::create_shader_module`].
+    fn shader_key(&self, module: &wgpu::ShaderModule) -> Option<&ShaderKey> {
+        self.shader_cache
+            .iter()
+            .find(|(_, cached)| *cached == module)
+            .map(|(key, _)| key)
+    }
+
+    /// Creates a render pipeline from shader modules returned by [`Self::create_shader_module`].
+    /// Pipelines are cached on the source and defines of both stages along with the rest of their
+    /// state, so each shader variant gets its own pipeline and requesting it again is free.
     pub fn create_render_pipeline(
-        &self,
+        &mut self,
         vertex: VertexShader,
         fragment: FragmentShader,
+        topology: wgpu::PrimitiveTopology,
     ) -> wgpu::RenderPipeline {
+        let key = match (
+            self.shader_key(vertex.module),
+            self.shader_key(fragment.module),
+        ) {
+            (Some(vertex_key), Some(fragment_key)) => Some(PipelineKey {
+                vertex: vertex_key.clone(),
+                vertex_entry_point: vertex.entry_point.map(str::to_string),
+                vertex_buffers: vertex
+                    .buffers
+                    .iter()
+                    .map(|layout| {
+                        (
+                            layout.array_stride,
+                            layout.step_mode,
+                            layout.attributes.to_vec(),
+                        )
+                    })
+                    .collect(),
+                fragment: fragment_key.clone(),
+                fragment_entry_point: fragment.entry_point.map(str::to_string),
+                topology,
+            }),
+            // Modules from elsewhere have no defines to key on, so their pipelines aren't cached.
+            _ => None,
+        };
+        if let Some(pipeline) = key.as_ref().and_then(|key| self.pipeline_cache.get(key)) {
+            return pipeline.clone();
+        }
+
         let render_pipeline_layout =
             self.device
                 .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
@@ -410,7 +651,8 @@ impl<App: Application> WindowSurface<App> {
                     push_constant_ranges: &[],
                 });
 
-        self.device
+        let pipeline = self
+            .device
             .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
                 label: Some("Render Pipeline"),
                 layout: Some(&render_pipeline_layout),
@@ -431,7 +673,7 @@ impl<App: Application> WindowSurface<App> {
                     compilation_options: wgpu::PipelineCompilationOptions::default(),
                 }),
                 primitive: wgpu::PrimitiveState {
-                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    topology,
                     strip_index_format: None,
                     front_face: wgpu::FrontFace::Ccw,
                     cull_mode: Some(wgpu::Face::Back),
@@ -447,6 +689,11 @@ impl<App: Application> WindowSurface<App> {
                 },
                 multiview: None,
                 cache: None,
-            })
+            });
+        if let Some(key) = key {
+            self.pipeline_cache.insert(key, pipeline.clone());
+        }
+
+        pipeline
     }
 }
@@ -1,10 +1,66 @@
-use crate::gravsim::shader::{FragmentShader, VertexShader};
+use std::time::{Duration, Instant};
+
+use glam::DVec3;
+
+use crate::gravsim::{
+    localization::{Locale, Text},
+    shader::{FragmentShader, ShaderDefines, VertexShader},
+    sim::{
+        Simulation, SimulationConfig, Solver, Timestep,
+        boundary::{Boundary, BoundaryShape, Walls},
+        collisions::Collisions,
+        initial_conditions::{DiscGalaxy, GalaxyCollision, Plummer, Spheroid},
+        integrator::IntegratorKind,
+        interaction_matrix::InteractionMatrixWindow,
+        units::UnitSystem,
+        watchdog::Watchdog,
+    },
+};
 
 mod gravsim;
 
 struct GravSimApp {
     render_pipeline: wgpu::RenderPipeline,
+    /// Draws every body white, built from the same shader without `VERTEX_COLORS`.
+    monochrome_pipeline: wgpu::RenderPipeline,
     wgpu_buffer: wgpu::Buffer,
+    /// The bodies' current positions in clip space, re-uploaded every frame.
+    vertices: Vec<Vertex>,
+    boundary_pipeline: wgpu::RenderPipeline,
+    boundary_buffer: wgpu::Buffer,
+    /// The boundary's outline in clip space, as a closed line strip.
+    boundary_vertices: Vec<Vertex>,
+    sim: Simulation,
+    watchdog: Watchdog,
+    /// The last total energy shown, with the simulated time and config it was computed for.
+    energy: Option<(f64, SimulationConfig, f64)>,
+    energy_computed: Instant,
+    show_interaction_matrix: bool,
+    interaction_matrix: InteractionMatrixWindow,
+    plummer: Plummer,
+    disc_galaxy: DiscGalaxy,
+    /// The disc galaxy's halo, kept while it is disabled.
+    halo: Spheroid,
+    galaxy_collision: GalaxyCollision,
+    /// The units new initial conditions are generated in. The running simulation keeps the units
+    /// it was generated in, as changing them would change G under its bodies.
+    units: UnitSystem,
+    /// The Barnes-Hut opening angle, kept while the direct solver is selected.
+    theta: f64,
+    /// The incremental tree update budget in milliseconds, or `None` to rebuild every step.
+    tree_budget_ms: Option<f32>,
+    /// The restitution used for bouncing collisions, kept while collisions are off.
+    restitution: f64,
+    /// The boundary's half size or radius and walls, kept while there is no boundary.
+    boundary_size: f64,
+    walls: Walls,
+    /// The last fixed and adaptive timestep settings, kept while the other is selected.
+    timesteps: [Timestep; 2],
+    /// Whether bodies are coloured by mass, or all drawn white.
+    color_by_mass: bool,
+    paused: bool,
+    time_scale: f32,
+    zoom: f32,
 }
 
 #[repr(C)]
@@ -35,70 +91,668 @@ impl Vertex {
     }
 }
 
-const VERTICES: &[Vertex] = &[
-    Vertex {
-        position: [-1.0, 1.0, 0.0],
-        color: [1.0, 0.0, 0.0],
-    },
-    Vertex {
-        position: [-1.0, -1.0, 0.0],
-        color: [0.0, 1.0, 0.0],
-    },
-    Vertex {
-        position: [1.0, 1.0, 0.0],
-        color: [0.0, 0.0, 1.0],
-    },
-];
+/// How many line segments approximate a spherical boundary's outline.
+const BOUNDARY_SEGMENTS: usize = 64;
+
+/// Dim enough that the boundary stays in the background behind the bodies.
+const BOUNDARY_COLOR: [f32; 3] = [0.2, 0.22, 0.28];
+
+/// The most wall-clock time a single frame advances the simulation by.
+const MAX_FRAME_TIME: Duration = Duration::from_millis(100);
+
+/// Above this many bodies the O(N²) total energy is too slow to show every second.
+const ENERGY_MAX_BODIES: usize = 4096;
+
+/// How often the total energy shown is recomputed.
+const ENERGY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How many bodies orbit the central mass in the initial system.
+const ORBITING_BODIES: usize = 256;
+
+/// Builds a heavy central body with lighter bodies on circular orbits around it,
+/// spread over a disc by the golden angle so no two share an orbit.
+fn initial_system(config: SimulationConfig) -> Simulation {
+    const CENTRAL_MASS: f64 = 1000.0;
+    let golden_angle = std::f64::consts::PI * (3.0 - 5.0f64.sqrt());
+
+    let mut sim = Simulation::new(config);
+    sim.add_body(DVec3::ZERO, DVec3::ZERO, CENTRAL_MASS, 0.5);
+    for i in 0..ORBITING_BODIES {
+        let radius = 2.0 + 8.0 * ((i as f64 + 0.5) / ORBITING_BODIES as f64).sqrt();
+        let (sin, cos) = (i as f64 * golden_angle).sin_cos();
+        let speed = (config.units.gravitational_constant() * CENTRAL_MASS / radius).sqrt();
+        sim.add_body(
+            DVec3::new(cos, sin, 0.0) * radius,
+            DVec3::new(-sin, cos, 0.0) * speed,
+            0.01,
+            0.05,
+        );
+    }
+    sim
+}
+
+impl GravSimApp {
+    /// Replaces the running simulation, e.g. with freshly generated initial conditions.
+    fn reset(&mut self, sim: Simulation) {
+        log::info!("Starting a new simulation with {} bodies", sim.len());
+        self.sim = sim;
+        self.watchdog = Watchdog::default();
+        self.energy = None;
+    }
+
+    /// The Barnes-Hut solver with the theta and tree budget set in the UI.
+    fn barnes_hut(&self) -> Solver {
+        Solver::BarnesHut {
+            theta: self.theta,
+            tree_budget: self
+                .tree_budget_ms
+                .map(|ms| Duration::from_secs_f32(ms.max(0.0) / 1000.0)),
+        }
+    }
+
+    /// The total energy, recomputed at most every `ENERGY_INTERVAL` since it is an O(N²) sum,
+    /// and only once the simulation has moved on or its config has changed.
+    fn energy(&mut self) -> Option<f64> {
+        if self.sim.is_empty() {
+            return None;
+        }
+        let current = (self.sim.time(), *self.sim.config());
+        let stale = self.energy.is_none_or(|(time, config, _)| {
+            (time, config) != current && self.energy_computed.elapsed() >= ENERGY_INTERVAL
+        });
+        if stale {
+            self.energy = Some((current.0, current.1, self.sim.total_energy()));
+            self.energy_computed = Instant::now();
+        }
+        self.energy.map(|(_, _, energy)| energy)
+    }
+}
 
 impl gravsim::application::Application for GravSimApp {
     fn new(ws: &mut gravsim::window_surface::WindowSurface<Self>) -> Self {
-        let shader = ws.create_shader_module("Shader", include_str!("shader.wgsl"));
-        let render_pipeline = ws.create_render_pipeline(
-            VertexShader {
-                module: &shader,
-                buffers: &[Vertex::desc()],
-                entry_point: Some("vs_main"),
-            },
-            FragmentShader {
-                module: &shader,
-                entry_point: Some("fs_main"),
-            },
-        );
+        let shader = |ws: &mut gravsim::window_surface::WindowSurface<Self>, vertex_colors| {
+            ws.create_shader_module(
+                "Shader",
+                include_str!("shader.wgsl"),
+                &ShaderDefines::new().with("VERTEX_COLORS", vertex_colors),
+            )
+            .expect("Failed to preprocess shader")
+        };
+        let colored_shader = shader(ws, true);
+        let monochrome_shader = shader(ws, false);
+        let mut pipeline = |module, topology| {
+            ws.create_render_pipeline(
+                VertexShader {
+                    module,
+                    buffers: &[Vertex::desc()],
+                    entry_point: Some("vs_main"),
+                },
+                FragmentShader {
+                    module,
+                    entry_point: Some("fs_main"),
+                },
+                topology,
+            )
+        };
+        let render_pipeline = pipeline(&colored_shader, wgpu::PrimitiveTopology::PointList);
+        let monochrome_pipeline = pipeline(&monochrome_shader, wgpu::PrimitiveTopology::PointList);
+        let boundary_pipeline = pipeline(&colored_shader, wgpu::PrimitiveTopology::LineStrip);
 
+        let sim = initial_system(SimulationConfig::default());
+        let vertices = vec![
+            Vertex {
+                position: [0.0; 3],
+                color: [0.0; 3],
+            };
+            sim.len()
+        ];
         let wgpu_buffer = ws.create_buffer(
             "Vertex Buffer",
-            bytemuck::cast_slice(VERTICES),
-            wgpu::BufferUsages::VERTEX,
+            bytemuck::cast_slice(&vertices),
+            wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        );
+
+        let boundary_vertices = Vec::with_capacity(BOUNDARY_SEGMENTS + 1);
+        let boundary_buffer = ws.create_buffer(
+            "Boundary Vertex Buffer",
+            bytemuck::cast_slice(
+                &[Vertex {
+                    position: [0.0; 3],
+                    color: [0.0; 3],
+                }; BOUNDARY_SEGMENTS + 1],
+            ),
+            wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
         );
 
         GravSimApp {
-            render_pipeline: render_pipeline,
+            render_pipeline,
+            monochrome_pipeline,
             wgpu_buffer,
+            vertices,
+            boundary_pipeline,
+            boundary_buffer,
+            boundary_vertices,
+            sim,
+            watchdog: Watchdog::default(),
+            energy: None,
+            energy_computed: Instant::now(),
+            show_interaction_matrix: false,
+            interaction_matrix: InteractionMatrixWindow::default(),
+            plummer: Plummer {
+                count: 1000,
+                total_mass: 1000.0,
+                scale_radius: 3.0,
+                seed: 0,
+            },
+            disc_galaxy: DiscGalaxy {
+                disc_count: 4000,
+                disc_mass: 1000.0,
+                scale_length: 2.0,
+                scale_height: 0.1,
+                bulge: Spheroid {
+                    count: 1000,
+                    mass: 250.0,
+                    scale_radius: 0.4,
+                },
+                halo: None,
+                seed: 0,
+            },
+            halo: Spheroid {
+                count: 4000,
+                mass: 3000.0,
+                scale_radius: 10.0,
+            },
+            galaxy_collision: GalaxyCollision {
+                separation: 30.0,
+                impact_parameter: 6.0,
+                relative_speed: 12.0,
+                inclination: 60f64.to_radians(),
+            },
+            units: UnitSystem::default(),
+            theta: 0.5,
+            tree_budget_ms: None,
+            restitution: 0.8,
+            boundary_size: 12.0,
+            walls: Walls::default(),
+            timesteps: [
+                SimulationConfig::default().timestep,
+                Timestep::Adaptive {
+                    accuracy: 1e-4,
+                    min: 1e-6,
+                    max: 1.0 / 60.0,
+                },
+            ],
+            color_by_mass: true,
+            paused: false,
+            time_scale: 1.0,
+            zoom: 0.08,
+        }
+    }
+
+    fn update(&mut self, dt: Duration) {
+        if self.paused || self.watchdog.tripped() {
+            return;
+        }
+        // A long frame, e.g. while the window was minimised, is not made up for all at once.
+        let dt = dt.min(MAX_FRAME_TIME);
+        self.sim.advance(dt.as_secs_f64() * self.time_scale as f64);
+        if self.watchdog.check(&self.sim) {
+            self.paused = true;
         }
     }
 
     fn render(&mut self, context: &mut gravsim::window_surface::RenderContext) {
+        // Bodies are projected straight down the z axis, with x scaled so the view is not stretched.
+        let scale = [self.zoom / context.aspect_ratio(), self.zoom];
+        self.vertices.resize(
+            self.sim.len(),
+            Vertex {
+                position: [0.0; 3],
+                color: [0.0; 3],
+            },
+        );
+        if self.wgpu_buffer.size() < std::mem::size_of_val(self.vertices.as_slice()) as u64 {
+            self.wgpu_buffer = context.create_buffer(
+                "Vertex Buffer",
+                bytemuck::cast_slice(&self.vertices),
+                wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            );
+        }
+        for ((vertex, position), mass) in self
+            .vertices
+            .iter_mut()
+            .zip(self.sim.positions())
+            .zip(self.sim.masses())
+        {
+            vertex.position = [
+                position.x as f32 * scale[0],
+                position.y as f32 * scale[1],
+                0.0,
+            ];
+            vertex.color = if *mass > 1.0 {
+                [1.0, 0.85, 0.4]
+            } else {
+                [0.7, 0.8, 1.0]
+            };
+        }
+        context.write_buffer(&self.wgpu_buffer, bytemuck::cast_slice(&self.vertices));
+
+        self.boundary_vertices.clear();
+        if let Some(boundary) = self.sim.config().boundary {
+            // Projected down the z axis, a box's outline is a square and a sphere's a circle.
+            let outline = match boundary.shape {
+                BoundaryShape::Box { half_size } => {
+                    [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+                        .into_iter()
+                        .cycle()
+                        .take(5)
+                        .map(|(x, y)| (x * half_size, y * half_size))
+                        .collect::<Vec<_>>()
+                }
+                BoundaryShape::Sphere { radius } => (0..=BOUNDARY_SEGMENTS)
+                    .map(|i| {
+                        let (sin, cos) =
+                            (std::f64::consts::TAU * i as f64 / BOUNDARY_SEGMENTS as f64).sin_cos();
+                        (cos * radius, sin * radius)
+                    })
+                    .collect(),
+            };
+            self.boundary_vertices
+                .extend(outline.into_iter().map(|(x, y)| Vertex {
+                    position: [x as f32 * scale[0], y as f32 * scale[1], 0.0],
+                    color: BOUNDARY_COLOR,
+                }));
+            context.write_buffer(
+                &self.boundary_buffer,
+                bytemuck::cast_slice(&self.boundary_vertices),
+            );
+        }
+
         context.render_pass(
             gravsim::window_surface::RenderPassDesc {
                 label: Some("Main Render Pass"),
                 clear_color: wgpu::Color::BLACK,
             },
             |pass| {
-                pass.set_pipeline(&self.render_pipeline);
+                if !self.boundary_vertices.is_empty() {
+                    pass.set_pipeline(&self.boundary_pipeline);
+                    pass.set_vertex_buffer(0, self.boundary_buffer.slice(..));
+                    pass.draw(0..self.boundary_vertices.len() as u32, 0..1);
+                }
+                pass.set_pipeline(if self.color_by_mass {
+                    &self.render_pipeline
+                } else {
+                    &self.monochrome_pipeline
+                });
                 pass.set_vertex_buffer(0, self.wgpu_buffer.slice(..));
-                pass.draw(0..VERTICES.len() as u32, 0..1);
+                pass.draw(0..self.vertices.len() as u32, 0..1);
             },
         );
     }
 
-    fn ui(&mut self, ui: &mut imgui::Ui) {
-        let mut showed = true;
-        ui.show_demo_window(&mut showed);
+    fn ui(&mut self, ui: &mut imgui::Ui, locale: Locale) {
+        if self.show_interaction_matrix {
+            self.interaction_matrix
+                .build(ui, locale, &self.sim, &mut self.show_interaction_matrix);
+        }
+
+        let title = format!("{}###Simulation", locale.text(Text::SimulationTitle));
+        ui.window(title).always_auto_resize(true).build(|| {
+            ui.text(format!("{}: {}", locale.text(Text::Bodies), self.sim.len()));
+            if self.sim.len() > ENERGY_MAX_BODIES {
+                ui.text_disabled(format!(
+                    "{} {}",
+                    locale.text(Text::EnergyLimit),
+                    ENERGY_MAX_BODIES
+                ));
+            } else if let Some(energy) = self.energy() {
+                ui.text(format!("{} {:.6}", locale.text(Text::Energy), energy));
+            }
+
+            let mut config = *self.sim.config();
+
+            ui.input_scalar(locale.text(Text::Softening), &mut config.softening)
+                .display_format("%.4f")
+                .step(0.001)
+                .build();
+            config.softening = config.softening.max(0.0);
+
+            let mut barnes_hut = matches!(config.solver, Solver::BarnesHut { .. });
+            let mut index = barnes_hut as usize;
+            let solvers = [locale.text(Text::Direct), locale.text(Text::BarnesHut)];
+            if ui.combo_simple_string(locale.text(Text::Solver), &mut index, &solvers) {
+                barnes_hut = index == 1;
+            }
+            ui.enabled(barnes_hut, || {
+                ui.slider(locale.text(Text::Theta), 0.0, 1.5, &mut self.theta);
+                let mut incremental = self.tree_budget_ms.is_some();
+                if ui.checkbox(locale.text(Text::IncrementalTree), &mut incremental) {
+                    self.tree_budget_ms = incremental.then_some(1.0);
+                }
+                if let Some(budget) = &mut self.tree_budget_ms {
+                    ui.slider_config(locale.text(Text::TreeBudget), 0.0, 10.0)
+                        .flags(imgui::SliderFlags::ALWAYS_CLAMP)
+                        .build(budget);
+                }
+            });
+            config.solver = if barnes_hut {
+                self.barnes_hut()
+            } else {
+                Solver::Direct
+            };
+
+            let mut index = IntegratorKind::ALL
+                .iter()
+                .position(|kind| *kind == config.integrator)
+                .unwrap_or_default();
+            if ui.combo(
+                locale.text(Text::Integrator),
+                &mut index,
+                IntegratorKind::ALL,
+                |kind| locale.text(kind.name()).into(),
+            ) {
+                config.integrator = IntegratorKind::ALL[index];
+            }
+
+            let mut bounce = matches!(config.collisions, Collisions::Bounce { .. });
+            ui.checkbox(locale.text(Text::BouncingCollisions), &mut bounce);
+            ui.enabled(bounce, || {
+                ui.slider(
+                    locale.text(Text::Restitution),
+                    0.0,
+                    1.0,
+                    &mut self.restitution,
+                );
+            });
+            config.collisions = if bounce {
+                Collisions::Bounce {
+                    restitution: self.restitution,
+                }
+            } else {
+                Collisions::None
+            };
+
+            let mut index = match config.boundary.map(|boundary| boundary.shape) {
+                None => 0,
+                Some(BoundaryShape::Box { .. }) => 1,
+                Some(BoundaryShape::Sphere { .. }) => 2,
+            };
+            let shapes = [
+                locale.text(Text::BoundaryNone),
+                locale.text(Text::BoundaryBox),
+                locale.text(Text::BoundarySphere),
+            ];
+            ui.combo_simple_string(locale.text(Text::Boundary), &mut index, &shapes);
+            ui.enabled(index != 0, || {
+                ui.input_scalar(locale.text(Text::BoundarySize), &mut self.boundary_size)
+                    .build();
+                let mut walls = Walls::ALL
+                    .iter()
+                    .position(|walls| *walls == self.walls)
+                    .unwrap_or_default();
+                if ui.combo(locale.text(Text::Walls), &mut walls, Walls::ALL, |walls| {
+                    locale.text(walls.name()).into()
+                }) {
+                    self.walls = Walls::ALL[walls];
+                }
+            });
+            self.boundary_size = self.boundary_size.max(f64::MIN_POSITIVE);
+            let shape = match index {
+                1 => Some(BoundaryShape::Box {
+                    half_size: self.boundary_size,
+                }),
+                2 => Some(BoundaryShape::Sphere {
+                    radius: self.boundary_size,
+                }),
+                _ => None,
+            };
+            config.boundary = shape.map(|shape| Boundary {
+                shape,
+                walls: self.walls,
+            });
+
+            let mut index = matches!(config.timestep, Timestep::Adaptive { .. }) as usize;
+            self.timesteps[index] = config.timestep;
+            let timesteps = [locale.text(Text::Fixed), locale.text(Text::Adaptive)];
+            ui.combo_simple_string(locale.text(Text::Timestep), &mut index, &timesteps);
+            match &mut self.timesteps[index] {
+                Timestep::Fixed { dt } => {
+                    ui.input_scalar(locale.text(Text::Step), dt)
+                        .display_format("%.6f")
+                        .build();
+                    *dt = dt.max(1e-9);
+                }
+                Timestep::Adaptive { accuracy, min, max } => {
+                    ui.input_scalar(locale.text(Text::Accuracy), accuracy)
+                        .display_format("%.2e")
+                        .build();
+                    ui.input_scalar(locale.text(Text::MinStep), min)
+                        .display_format("%.2e")
+                        .build();
+                    ui.input_scalar(locale.text(Text::MaxStep), max)
+                        .display_format("%.6f")
+                        .build();
+                    *accuracy = accuracy.max(1e-12);
+                    *min = min.max(1e-9);
+                    *max = max.max(*min);
+                }
+            }
+            config.timestep = self.timesteps[index];
+            ui.text(format!(
+                "{} {:.2e}",
+                locale.text(Text::LastStep),
+                self.sim.last_dt()
+            ));
+
+            self.sim.set_config(config);
+
+            let initial_conditions = format!(
+                "{}###InitialConditions",
+                locale.text(Text::InitialConditions)
+            );
+            if ui.collapsing_header(initial_conditions, imgui::TreeNodeFlags::empty()) {
+                let mut index = UnitSystem::ALL
+                    .iter()
+                    .position(|units| *units == self.units)
+                    .unwrap_or_default();
+                if ui.combo(
+                    locale.text(Text::Units),
+                    &mut index,
+                    UnitSystem::ALL,
+                    |units| locale.text(units.name()).into(),
+                ) {
+                    self.units = UnitSystem::ALL[index];
+                }
+                let config = SimulationConfig {
+                    units: self.units,
+                    ..config
+                };
+
+                ui.separator();
+                if ui.button(locale.text(Text::OrbitingBodies)) {
+                    self.reset(initial_system(config));
+                }
+
+                ui.separator();
+                let mut count = self.plummer.count as i32;
+                ui.input_int(
+                    format!("{}##Plummer", locale.text(Text::Bodies)),
+                    &mut count,
+                )
+                .step(100)
+                .build();
+                self.plummer.count = count.max(1) as usize;
+                ui.input_scalar(locale.text(Text::TotalMass), &mut self.plummer.total_mass)
+                    .build();
+                ui.input_scalar(
+                    locale.text(Text::ScaleRadius),
+                    &mut self.plummer.scale_radius,
+                )
+                .build();
+                ui.input_scalar(
+                    format!("{}##Plummer", locale.text(Text::Seed)),
+                    &mut self.plummer.seed,
+                )
+                .build();
+                self.plummer.total_mass = self.plummer.total_mass.max(f64::MIN_POSITIVE);
+                self.plummer.scale_radius = self.plummer.scale_radius.max(f64::MIN_POSITIVE);
+                if ui.button(locale.text(Text::PlummerSphere)) {
+                    let mut sim = Simulation::new(config);
+                    self.plummer.generate(&mut sim);
+                    self.reset(sim);
+                }
+
+                ui.separator();
+                let galaxy = &mut self.disc_galaxy;
+                component_inputs(
+                    ui,
+                    locale,
+                    Text::Disc,
+                    &mut galaxy.disc_count,
+                    &mut galaxy.disc_mass,
+                    None,
+                );
+                ui.input_scalar(locale.text(Text::ScaleLength), &mut galaxy.scale_length)
+                    .build();
+                ui.input_scalar(locale.text(Text::ScaleHeight), &mut galaxy.scale_height)
+                    .build();
+                galaxy.scale_length = galaxy.scale_length.max(f64::MIN_POSITIVE);
+                galaxy.scale_height = galaxy.scale_height.max(0.0);
+                let bulge = &mut galaxy.bulge;
+                component_inputs(
+                    ui,
+                    locale,
+                    Text::Bulge,
+                    &mut bulge.count,
+                    &mut bulge.mass,
+                    Some(&mut bulge.scale_radius),
+                );
+                let mut halo = galaxy.halo.is_some();
+                ui.checkbox(locale.text(Text::Halo), &mut halo);
+                ui.enabled(halo, || {
+                    component_inputs(
+                        ui,
+                        locale,
+                        Text::Halo,
+                        &mut self.halo.count,
+                        &mut self.halo.mass,
+                        Some(&mut self.halo.scale_radius),
+                    );
+                });
+                galaxy.halo = halo.then_some(self.halo);
+                ui.input_scalar(
+                    format!("{}##Disc", locale.text(Text::Seed)),
+                    &mut galaxy.seed,
+                )
+                .build();
+                if ui.button(locale.text(Text::DiscGalaxy)) {
+                    // Galaxies have thousands of bodies, far too many for the direct solver.
+                    let mut sim = Simulation::new(SimulationConfig {
+                        solver: self.barnes_hut(),
+                        ..config
+                    });
+                    self.disc_galaxy.generate(&mut sim);
+                    self.reset(sim);
+                }
+
+                ui.separator();
+                let collision = &mut self.galaxy_collision;
+                ui.input_scalar(locale.text(Text::Separation), &mut collision.separation)
+                    .build();
+                ui.input_scalar(
+                    locale.text(Text::ImpactParameter),
+                    &mut collision.impact_parameter,
+                )
+                .build();
+                ui.input_scalar(
+                    locale.text(Text::RelativeSpeed),
+                    &mut collision.relative_speed,
+                )
+                .build();
+                let mut inclination = collision.inclination.to_degrees() as f32;
+                if ui.slider(locale.text(Text::Inclination), 0.0, 180.0, &mut inclination) {
+                    collision.inclination = (inclination as f64).to_radians();
+                }
+                if ui.button(locale.text(Text::GalaxyCollision)) {
+                    let mut sim = Simulation::new(SimulationConfig {
+                        solver: self.barnes_hut(),
+                        ..config
+                    });
+                    self.galaxy_collision.generate(&self.disc_galaxy, &mut sim);
+                    self.reset(sim);
+                }
+            }
+
+            ui.checkbox(locale.text(Text::ColorByMass), &mut self.color_by_mass);
+            ui.checkbox(
+                locale.text(Text::InteractionMatrixTitle),
+                &mut self.show_interaction_matrix,
+            );
+            ui.checkbox(locale.text(Text::Paused), &mut self.paused);
+            ui.slider(locale.text(Text::TimeScale), 0.0, 4.0, &mut self.time_scale);
+            ui.slider(locale.text(Text::Zoom), 0.01, 0.5, &mut self.zoom);
+        });
+    }
+
+    fn hud(&mut self, ui: &mut imgui::Ui, locale: Locale) {
+        self.watchdog.dialog(ui, locale, &mut self.sim);
+
+        let units = self.sim.config().units;
+        let display_size = ui.io().display_size;
+        ui.window("##Hud")
+            .position([10.0, display_size[1] - 10.0], imgui::Condition::Always)
+            .position_pivot([0.0, 1.0])
+            .bg_alpha(0.35)
+            .flags(
+                imgui::WindowFlags::NO_DECORATION
+                    | imgui::WindowFlags::ALWAYS_AUTO_RESIZE
+                    | imgui::WindowFlags::NO_INPUTS
+                    | imgui::WindowFlags::NO_NAV
+                    | imgui::WindowFlags::NO_FOCUS_ON_APPEARING,
+            )
+            .build(|| {
+                ui.text(format!("t = {}", units.format_time(self.sim.time())));
+                if self.paused {
+                    ui.text(locale.text(Text::Paused));
+                } else {
+                    ui.text(format!(
+                        "{:.2}x ({}/s)",
+                        self.time_scale,
+                        units.format_time(self.time_scale as f64)
+                    ));
+                }
+            });
+    }
+}
+
+/// Inputs for a galaxy component's body count, mass and, for spheroids, scale radius,
+/// labelled with the component's `name`.
+fn component_inputs(
+    ui: &imgui::Ui,
+    locale: Locale,
+    name: Text,
+    count: &mut usize,
+    mass: &mut f64,
+    scale_radius: Option<&mut f64>,
+) {
+    let label = |quantity| format!("{} ({})", locale.text(quantity), locale.text(name));
+    let mut value = *count as i32;
+    ui.input_int(label(Text::Bodies), &mut value)
+        .step(100)
+        .build();
+    *count = value.max(0) as usize;
+    ui.input_scalar(label(Text::Mass), mass).build();
+    *mass = mass.max(0.0);
+    if let Some(scale_radius) = scale_radius {
+        ui.input_scalar(label(Text::ScaleRadius), scale_radius)
+            .build();
+        *scale_radius = scale_radius.max(f64::MIN_POSITIVE);
     }
 }
 
 fn main() {
-    env_logger::init();
+    gravsim::logging::init();
+    gravsim::crash::install_panic_hook();
     log::info!("Starting application.");
 
     let exit_sate = gravsim::application::run_app::<GravSimApp>();
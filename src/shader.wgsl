@@ -18,5 +18,9 @@ fn vs_main(model: VertexInput) -> VertexOutput {
 
 @fragment
 fn fs_main(@location(0) in_color: vec3<f32>) -> @location(0) vec4<f32> {
+#if VERTEX_COLORS
     return vec4<f32>(in_color, 1.0);
+#else
+    return vec4<f32>(1.0, 1.0, 1.0, 1.0);
+#endif
 }
\ No newline at end of file